@@ -11,8 +11,13 @@ fn main() {
         .add_plugins(DefaultPlugins)
         .add_plugin(PlayerPlugin)
         .insert_resource(MovementSettings {
-            sensitivity: 0.00015, // default: 0.00012
-            speed: 12.0,          // default: 12.0
+            sensitivity: 0.00015,  // default: 0.00012
+            speed: 12.0,           // default: 12.0
+            boost_multiplier: 3.0, // default: 3.0
+            slow_multiplier: 0.25, // default: 0.25
+            acceleration: 60.0,    // default: 60.0
+            friction: 10.0,        // default: 10.0
+            ..Default::default()
         })
         .add_startup_system(setup)
         .run();
@@ -45,6 +50,7 @@ fn setup(
 
     info!("Move camera around by using WASD for lateral movement");
     info!("Use Left Shift and Spacebar for vertical movement");
+    info!("Hold Left Ctrl to move faster and Left Alt to move slower");
     info!("Use the mouse to look around");
     info!("Press Esc to hide or show the mouse cursor");
 }
@@ -1,29 +1,20 @@
-use bevy::{input::mouse::MouseWheel, prelude::*, render::camera::Projection};
-use bevy_flycam::{FlyCam, MovementSettings, NoCameraPlayerPlugin};
+use bevy::prelude::*;
+use bevy_flycam::{FlyCam, NoCameraPlayerPlugin, ScrollSettings};
 
 // From bevy examples:
 // https://github.com/bevyengine/bevy/blob/latest/examples/3d/3d_scene.rs
 
-#[derive(Clone, Eq, PartialEq, Debug, Hash)]
-enum ScrollType {
-    MovementSpeed,
-    Zoom,
-}
-
 fn main() {
     App::new()
         .insert_resource(Msaa { samples: 4 })
         .add_plugins(DefaultPlugins)
         //NoCameraPlayerPlugin as we provide the camera
         .add_plugin(NoCameraPlayerPlugin)
-        .insert_resource(MovementSettings {
+        .insert_resource(ScrollSettings {
+            enabled: true,
             ..Default::default()
         })
-        // Setting initial state
-        .add_state(ScrollType::MovementSpeed)
         .add_startup_system(setup)
-        .add_system(switch_scroll_type)
-        .add_system(scroll)
         .run();
 }
 
@@ -61,45 +52,6 @@ fn setup(
     // add plugin
     commands.spawn(camera).insert(FlyCam);
 
-    info!("Press 'Z' to switch between Movement Speed and Zoom");
-    info!("Changing the selected value by scrolling the mousewheel");
-}
-
-/// Listens for Z key being pressed and toggles between the two scroll-type states [`ScrollType`]
-#[allow(unused_must_use)]
-fn switch_scroll_type(
-    mut scroll_type: ResMut<State<ScrollType>>,
-    keyboard_input: Res<Input<KeyCode>>,
-) {
-    if keyboard_input.just_pressed(KeyCode::Z) {
-        let result = match scroll_type.current() {
-            ScrollType::MovementSpeed => ScrollType::Zoom,
-            ScrollType::Zoom => ScrollType::MovementSpeed,
-        };
-
-        println!("{:?}", result);
-        scroll_type.set(result);
-    }
-}
-
-/// Depending on the state, the mouse-scroll changes either the movement speed or the field-of-view of the camera
-fn scroll(
-    mut settings: ResMut<MovementSettings>,
-    scroll_type: Res<State<ScrollType>>,
-    mut mouse_wheel_events: EventReader<MouseWheel>,
-    mut query: Query<(&FlyCam, &mut Projection)>,
-) {
-    for event in mouse_wheel_events.iter() {
-        if *scroll_type.current() == ScrollType::MovementSpeed {
-            settings.speed = (settings.speed + event.y * 0.1).abs();
-            println!("Speed: {:?}", settings.speed);
-        } else {
-            for (_camera, project) in query.iter_mut() {
-                if let Projection::Perspective(perspective) = project.into_inner() {
-                    perspective.fov = (perspective.fov - event.y * 0.01).abs();
-                    println!("FOV: {:?}", perspective.fov);
-                }
-            }
-        }
-    }
+    info!("Scroll the mousewheel to change the movement speed");
+    info!("Hold 'Z' while scrolling to zoom instead");
 }
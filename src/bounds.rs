@@ -0,0 +1,186 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::{FlyCam, FlyCamVelocity, MovementSettings};
+
+/// Axis-aligned box a [`FlyCam`] may move in, see [`MovementSettings::bounds`]
+#[derive(Clone, Copy, Debug, PartialEq, Reflect, FromReflect)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct BoundsBox {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl BoundsBox {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    /// Point of the box closest to `point`
+    fn clamp(&self, point: Vec3) -> Vec3 {
+        point.clamp(self.min.min(self.max), self.min.max(self.max))
+    }
+}
+
+/// Sent when a [`FlyCam`] reaches the edge of its [`MovementSettings::bounds`]
+///
+/// Sent once when the camera is first pushed back, and again only after it has
+/// moved freely for a frame.
+#[derive(Clone, Copy, Debug, PartialEq, Reflect, FromReflect)]
+pub struct FlyCamBoundsHit {
+    pub entity: Entity,
+    /// Closest point inside the bounds, where the camera is pushed back to
+    pub position: Vec3,
+}
+
+/// Moves `translation` back towards the closest point inside `settings.bounds`,
+/// removing the part of `velocity` that leads further out
+///
+/// A camera that is already `outside` by some distance cannot get further out than
+/// that, so holding a movement key against the edge does not keep it out. Returns the
+/// closest point inside and how far outside the camera is left, or `None` if it is
+/// inside.
+fn push_back(
+    translation: &mut Vec3,
+    velocity: Option<&mut Vec3>,
+    settings: &MovementSettings,
+    outside: Option<f32>,
+    dt: f32,
+) -> Option<(Vec3, f32)> {
+    // Inside the union of the boxes, the closest point is the translation itself
+    let inside = settings
+        .bounds
+        .iter()
+        .map(|bounds| bounds.clamp(*translation))
+        .min_by(|a, b| {
+            a.distance_squared(*translation)
+                .total_cmp(&b.distance_squared(*translation))
+        })
+        .filter(|inside| inside != translation)?;
+
+    let mut offset = inside - *translation;
+    let inward = offset.normalize();
+    // Stop moving out instead of pushing against the edge every frame
+    if let Some(velocity) = velocity {
+        let outward = -velocity.dot(inward);
+        if outward > 0. {
+            *velocity += inward * outward;
+        }
+    }
+
+    if settings.bounds_stiffness.is_infinite() {
+        *translation = inside;
+        return Some((inside, 0.));
+    }
+    if let Some(outside) = outside {
+        offset = offset.clamp_length_max(outside);
+    }
+    let blend = 1. - (-settings.bounds_stiffness * dt).exp();
+    offset *= 1. - blend;
+    *translation = inside - offset;
+    Some((inside, offset.length()))
+}
+
+/// Keeps every [`FlyCam`] inside its [`MovementSettings::bounds`]
+#[allow(clippy::type_complexity)]
+pub(crate) fn clamp_to_bounds(
+    time: Res<Time>,
+    settings: Res<MovementSettings>,
+    mut hits: EventWriter<FlyCamBoundsHit>,
+    mut pushed: Local<HashMap<Entity, f32>>,
+    mut query: Query<
+        (
+            Entity,
+            &mut Transform,
+            Option<&mut FlyCamVelocity>,
+            Option<&MovementSettings>,
+        ),
+        With<FlyCam>,
+    >,
+) {
+    let mut still_pushed = HashMap::default();
+    for (entity, mut transform, velocity, cam_settings) in query.iter_mut() {
+        let settings = cam_settings.unwrap_or(&settings);
+        let mut translation = transform.translation;
+        let outside = pushed.get(&entity).copied();
+        let Some((inside, left_outside)) = push_back(
+            &mut translation,
+            velocity.map(|velocity| &mut velocity.into_inner().0),
+            settings,
+            outside,
+            time.delta_seconds(),
+        ) else {
+            continue;
+        };
+
+        if outside.is_none() {
+            hits.send(FlyCamBoundsHit {
+                entity,
+                position: inside,
+            });
+        }
+        still_pushed.insert(entity, left_outside);
+        transform.translation = translation;
+    }
+    *pushed = still_pushed;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(bounds_stiffness: f32) -> MovementSettings {
+        MovementSettings {
+            bounds: vec![BoundsBox::new(Vec3::splat(-10.), Vec3::splat(10.))],
+            bounds_stiffness,
+            ..MovementSettings::instant()
+        }
+    }
+
+    /// Holds a movement key towards +X from just inside the edge for two seconds
+    fn hold_against_edge(settings: &MovementSettings) -> (Vec3, Vec3) {
+        let dt = 1. / 60.;
+        let mut translation = Vec3::new(9.9, 0., 0.);
+        let mut velocity = Vec3::ZERO;
+        let mut outside = None;
+        for _ in 0..120 {
+            let moved;
+            (velocity, moved) = settings.accelerate(velocity, Vec3::X * settings.speed, dt);
+            translation += moved;
+            outside = push_back(&mut translation, Some(&mut velocity), settings, outside, dt)
+                .map(|(_, outside)| outside);
+        }
+        (translation, velocity)
+    }
+
+    #[test]
+    fn hard_bounds_hold_camera_at_edge() {
+        let (translation, velocity) = hold_against_edge(&settings(f32::INFINITY));
+        assert_eq!(translation.x, 10.);
+        assert_eq!(velocity.x, 0.);
+    }
+
+    #[test]
+    fn soft_bounds_bring_held_camera_back() {
+        let settings = settings(5.);
+        let (translation, velocity) = hold_against_edge(&settings);
+        // One frame of movement is the furthest it gets, and it is pushed back from there
+        assert!(translation.x - 10. < 0.01, "{translation}");
+        assert_eq!(velocity.x, 0.);
+    }
+
+    #[test]
+    fn inside_is_left_alone() {
+        let mut translation = Vec3::new(1., 2., 3.);
+        let mut velocity = Vec3::X;
+        let pushed = push_back(
+            &mut translation,
+            Some(&mut velocity),
+            &settings(5.),
+            None,
+            1. / 60.,
+        );
+        assert!(pushed.is_none());
+        assert_eq!((translation, velocity), (Vec3::new(1., 2., 3.), Vec3::X));
+    }
+}
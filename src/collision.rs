@@ -0,0 +1,377 @@
+use bevy::prelude::*;
+
+/// Shape the active [`FlyCam`](crate::FlyCam) collides with when [`CollisionSettings`] are enabled
+///
+/// Placed at the entity's `GlobalTransform`. Boxes stay axis-aligned and only
+/// follow its translation and scale, while planes also follow its rotation.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect, FromReflect)]
+#[reflect(Component)]
+pub enum FlyCamCollider {
+    /// Axis-aligned box extending `half_extents` from its center
+    Aabb {
+        half_extents: Vec3,
+    },
+    Sphere {
+        radius: f32,
+    },
+    /// Half-space behind a plane through the entity, facing `normal`
+    Plane {
+        normal: Vec3,
+    },
+}
+
+impl Default for FlyCamCollider {
+    fn default() -> Self {
+        FlyCamCollider::Sphere { radius: 0.5 }
+    }
+}
+
+impl FlyCamCollider {
+    /// Smallest offset moving a sphere at `point` with `radius` out of this collider
+    fn penetration(&self, global: &GlobalTransform, point: Vec3, radius: f32) -> Option<Vec3> {
+        let (scale, rotation, center) = global.to_scale_rotation_translation();
+        match *self {
+            FlyCamCollider::Aabb { half_extents } => {
+                let half_extents = half_extents * scale.abs();
+                let closest = point.clamp(center - half_extents, center + half_extents);
+                let outside = point - closest;
+                if outside != Vec3::ZERO {
+                    let distance = outside.length();
+                    return (distance < radius).then(|| outside / distance * (radius - distance));
+                }
+
+                // Inside the box, leave through the nearest face
+                let local = point - center;
+                let depth = half_extents - local.abs();
+                let axis = if depth.x <= depth.y && depth.x <= depth.z {
+                    Vec3::X
+                } else if depth.y <= depth.z {
+                    Vec3::Y
+                } else {
+                    Vec3::Z
+                };
+                let sign = if local.dot(axis) < 0. { -1. } else { 1. };
+                Some(axis * sign * (depth.dot(axis) + radius))
+            }
+            FlyCamCollider::Sphere { radius: sphere } => {
+                let reach = sphere * scale.max_element() + radius;
+                let offset = point - center;
+                let distance = offset.length();
+                (distance < reach).then(|| {
+                    let normal = offset.try_normalize().unwrap_or(Vec3::Y);
+                    normal * (reach - distance)
+                })
+            }
+            FlyCamCollider::Plane { normal } => {
+                let normal = (rotation * normal).try_normalize()?;
+                let distance = (point - center).dot(normal);
+                (distance < radius).then(|| normal * (radius - distance))
+            }
+        }
+    }
+
+    /// Earliest fraction of `delta` at which a sphere with `radius` moving from `start`
+    /// touches this collider, and the surface normal there
+    ///
+    /// Spheres already touching it are left to [`FlyCamCollider::penetration`].
+    fn sweep(
+        &self,
+        global: &GlobalTransform,
+        start: Vec3,
+        delta: Vec3,
+        radius: f32,
+    ) -> Option<(f32, Vec3)> {
+        let (scale, rotation, center) = global.to_scale_rotation_translation();
+        match *self {
+            FlyCamCollider::Aabb { half_extents } => {
+                // The box grown by the radius, which hits slightly early at its corners
+                let half_extents = half_extents * scale.abs() + radius;
+                let (min, max) = (center - half_extents, center + half_extents);
+                let (mut enter, mut exit) = (0f32, 1f32);
+                let mut normal = Vec3::ZERO;
+                for axis in 0..3 {
+                    if delta[axis] == 0. {
+                        if start[axis] < min[axis] || start[axis] > max[axis] {
+                            return None;
+                        }
+                        continue;
+                    }
+                    let a = (min[axis] - start[axis]) / delta[axis];
+                    let b = (max[axis] - start[axis]) / delta[axis];
+                    if a.min(b) > enter {
+                        enter = a.min(b);
+                        normal = Vec3::ZERO;
+                        normal[axis] = -delta[axis].signum();
+                    }
+                    exit = exit.min(a.max(b));
+                    if enter > exit {
+                        return None;
+                    }
+                }
+                (normal != Vec3::ZERO).then_some((enter, normal))
+            }
+            FlyCamCollider::Sphere { radius: sphere } => {
+                let reach = sphere * scale.max_element() + radius;
+                let offset = start - center;
+                let approach = offset.dot(delta);
+                let outside = offset.length_squared() - reach * reach;
+                if outside <= 0. || approach >= 0. {
+                    return None;
+                }
+                let discriminant = approach * approach - delta.length_squared() * outside;
+                if discriminant < 0. {
+                    return None;
+                }
+                let t = (-approach - discriminant.sqrt()) / delta.length_squared();
+                (t <= 1.).then(|| (t, (offset + delta * t).normalize_or_zero()))
+            }
+            FlyCamCollider::Plane { normal } => {
+                let normal = (rotation * normal).try_normalize()?;
+                let distance = (start - center).dot(normal) - radius;
+                let approach = delta.dot(normal);
+                if distance < 0. || approach >= 0. {
+                    return None;
+                }
+                let t = -distance / approach;
+                (t <= 1.).then_some((t, normal))
+            }
+        }
+    }
+}
+
+/// Collision of the active flycam against [`FlyCamCollider`]s
+///
+/// Disabled by default. When enabled, the camera is kept `radius` away from every
+/// collider and slides along them instead of stopping. Can be
+/// [overridden per camera](crate#per-camera-settings).
+#[derive(Resource, Component, Clone, Reflect)]
+#[reflect(Resource, Component)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct CollisionSettings {
+    pub enabled: bool,
+    pub radius: f32,
+    /// Push-out passes per step, more resolve corners between colliders better
+    pub iterations: u32,
+}
+
+impl Default for CollisionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            radius: 0.3,
+            iterations: 4,
+        }
+    }
+}
+
+/// Most steps a single frame of movement is split into before sweeping instead
+const MAX_STEPS: u32 = 16;
+
+/// Moves `translation` by `delta`, pushing it out of colliders and removing the part
+/// of `velocity` that goes into them
+///
+/// The movement is split into steps no longer than the radius so fast cameras do not
+/// pass through thin colliders. Movements too long for [`MAX_STEPS`] are swept
+/// against every collider instead.
+pub(crate) fn move_and_slide(
+    translation: &mut Vec3,
+    velocity: &mut Vec3,
+    delta: Vec3,
+    settings: &CollisionSettings,
+    colliders: &[(&FlyCamCollider, &GlobalTransform)],
+) {
+    let radius = settings.radius.max(f32::EPSILON);
+    let steps = (delta.length() / radius).ceil();
+    if steps > MAX_STEPS as f32 {
+        sweep_and_slide(translation, velocity, delta, radius, colliders);
+        let mut rest = Vec3::ZERO;
+        push_out(
+            translation,
+            velocity,
+            &mut rest,
+            radius,
+            settings,
+            colliders,
+        );
+        return;
+    }
+
+    let steps = (steps as u32).max(1);
+    let mut step = delta / steps as f32;
+    for _ in 0..steps {
+        *translation += step;
+        push_out(
+            translation,
+            velocity,
+            &mut step,
+            radius,
+            settings,
+            colliders,
+        );
+    }
+}
+
+/// Pushes `translation` out of every collider it is inside of, removing the part of
+/// `velocity` and `step` that goes into them so they slide along the surface
+fn push_out(
+    translation: &mut Vec3,
+    velocity: &mut Vec3,
+    step: &mut Vec3,
+    radius: f32,
+    settings: &CollisionSettings,
+    colliders: &[(&FlyCamCollider, &GlobalTransform)],
+) {
+    for _ in 0..settings.iterations {
+        let mut resolved = true;
+        for (collider, global) in colliders {
+            if let Some(push) = collider.penetration(global, *translation, radius) {
+                *translation += push;
+                if let Some(normal) = push.try_normalize() {
+                    *velocity -= normal * velocity.dot(normal).min(0.);
+                    *step -= normal * step.dot(normal).min(0.);
+                }
+                resolved = false;
+            }
+        }
+        if resolved {
+            break;
+        }
+    }
+}
+
+/// Moves `translation` by `delta` up to the first collider in the way, then slides
+/// the rest of the way along it
+fn sweep_and_slide(
+    translation: &mut Vec3,
+    velocity: &mut Vec3,
+    delta: Vec3,
+    radius: f32,
+    colliders: &[(&FlyCamCollider, &GlobalTransform)],
+) {
+    let mut remaining = delta;
+    // Each hit removes one direction, so a few passes are enough to settle in a corner
+    for _ in 0..4 {
+        let hit = colliders
+            .iter()
+            .filter_map(|(collider, global)| {
+                collider.sweep(global, *translation, remaining, radius)
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+        let Some((t, normal)) = hit else {
+            *translation += remaining;
+            return;
+        };
+
+        *translation += remaining * t;
+        remaining *= 1. - t;
+        remaining -= normal * remaining.dot(normal).min(0.);
+        *velocity -= normal * velocity.dot(normal).min(0.);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> CollisionSettings {
+        CollisionSettings {
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    /// Wall 0.02 thick whose near face is at `z = -4.99`
+    fn thin_wall() -> (FlyCamCollider, GlobalTransform) {
+        let wall = FlyCamCollider::Aabb {
+            half_extents: Vec3::new(10., 10., 0.01),
+        };
+        (wall, GlobalTransform::from_xyz(0., 0., -5.))
+    }
+
+    #[test]
+    fn fast_move_stops_at_thin_wall() {
+        let (wall, global) = thin_wall();
+        // Stepped, and long enough to be swept
+        for distance in [4.8, 6., 100., 10_000.] {
+            let mut translation = Vec3::ZERO;
+            let mut velocity = Vec3::NEG_Z * distance;
+            let delta = velocity;
+            move_and_slide(
+                &mut translation,
+                &mut velocity,
+                delta,
+                &settings(),
+                &[(&wall, &global)],
+            );
+            let stop = -4.99 + settings().radius;
+            assert!(
+                (translation.z - stop).abs() < 1e-3,
+                "{distance}: {translation}"
+            );
+            assert_eq!(velocity.z, 0.);
+        }
+    }
+
+    #[test]
+    fn slides_along_wall() {
+        let (wall, global) = thin_wall();
+        for distance in [6., 100.] {
+            let mut translation = Vec3::ZERO;
+            let mut velocity = Vec3::new(1., 0., -1.) * distance;
+            let delta = velocity;
+            move_and_slide(
+                &mut translation,
+                &mut velocity,
+                delta,
+                &settings(),
+                &[(&wall, &global)],
+            );
+            assert!(
+                (translation.x - distance).abs() < 1e-3,
+                "{distance}: {translation}"
+            );
+            assert!(translation.z > -5.);
+            assert_eq!(velocity, Vec3::X * distance);
+        }
+    }
+
+    #[test]
+    fn pushed_out_of_sphere() {
+        let sphere = FlyCamCollider::Sphere { radius: 1. };
+        let global = GlobalTransform::from_xyz(0., 0., 0.);
+        let mut translation = Vec3::new(0.5, 0., 0.);
+        let mut velocity = Vec3::NEG_X;
+        move_and_slide(
+            &mut translation,
+            &mut velocity,
+            Vec3::ZERO,
+            &settings(),
+            &[(&sphere, &global)],
+        );
+        assert!(
+            (translation - Vec3::X * 1.3).length() < 1e-5,
+            "{translation}"
+        );
+        assert_eq!(velocity, Vec3::ZERO);
+    }
+
+    #[test]
+    fn pushed_out_of_plane() {
+        let plane = FlyCamCollider::Plane { normal: Vec3::Y };
+        let global = GlobalTransform::from_xyz(0., 2., 0.);
+        let mut translation = Vec3::new(3., 1., 0.);
+        let mut velocity = Vec3::new(1., -1., 0.);
+        move_and_slide(
+            &mut translation,
+            &mut velocity,
+            Vec3::ZERO,
+            &settings(),
+            &[(&plane, &global)],
+        );
+        assert!(
+            (translation - Vec3::new(3., 2.3, 0.)).length() < 1e-5,
+            "{translation}"
+        );
+        assert_eq!(velocity, Vec3::X);
+    }
+}
@@ -0,0 +1,412 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::BoxedFuture;
+use ron::error::SpannedError;
+use ron::value::RawValue;
+use serde::de::{DeserializeOwned, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{
+    CollisionSettings, CursorGrabSettings, GamepadControls, KeysBindings, MovementSettings,
+    OrbitSettings, ScrollSettings, WalkSettings,
+};
+
+/// Every flycam settings resource as stored in a RON file
+///
+/// Each section holds one resource, e.g. `movement` the [`MovementSettings`] and
+/// `cursor` the [`CursorGrabSettings`]. Can also be loaded as an asset from files
+/// ending in `.flycam.ron`, see [`FlyCamConfigAssetPlugin`]. Every section and field
+/// is optional:
+///
+/// ```ron
+/// (
+///     movement: (
+///         sensitivity: 0.00015,
+///         speed: 20.0,
+///     ),
+///     bindings: (
+///         forward: [Key(W), Key(Up)],
+///         up: [Key(Space), Mouse(Right)],
+///     ),
+/// )
+/// ```
+#[derive(Clone, Default, Serialize, Deserialize, TypeUuid, Reflect)]
+#[uuid = "70f00623-47e8-4ee9-869e-70eb75a24ac0"]
+pub struct FlyCamConfig {
+    pub movement: MovementSettings,
+    pub bindings: KeysBindings,
+    pub gamepad: GamepadControls,
+    pub scroll: ScrollSettings,
+    pub cursor: CursorGrabSettings,
+    pub orbit: OrbitSettings,
+    pub walk: WalkSettings,
+    pub collision: CollisionSettings,
+}
+
+/// Names of the sections of a [`FlyCamConfig`] file
+const SECTIONS: [&str; 8] = [
+    "movement",
+    "bindings",
+    "gamepad",
+    "scroll",
+    "cursor",
+    "orbit",
+    "walk",
+    "collision",
+];
+
+impl FlyCamConfig {
+    /// Parses a settings file
+    ///
+    /// Missing, invalid and unknown fields are replaced by their defaults and listed in
+    /// a warning. Only a file that is not valid RON at all is an error.
+    pub fn from_ron(text: &str) -> Result<Self, SpannedError> {
+        // An empty file is a file with every section missing
+        let sections = if text.trim().is_empty() {
+            Vec::new()
+        } else {
+            ron::from_str::<RawFields>(text)?.0
+        };
+        let mut problems = Vec::new();
+        let mut config = Self::default();
+
+        for name in SECTIONS {
+            if !sections.iter().any(|(section, _)| section == name) {
+                problems.push(format!("`{name}` is missing, using defaults"));
+            }
+        }
+        for (name, value) in &sections {
+            match name.as_str() {
+                "movement" => config.movement = lenient(value, name, &mut problems),
+                "bindings" => config.bindings = lenient(value, name, &mut problems),
+                "gamepad" => config.gamepad = lenient(value, name, &mut problems),
+                "scroll" => config.scroll = lenient(value, name, &mut problems),
+                "cursor" => config.cursor = lenient(value, name, &mut problems),
+                "orbit" => config.orbit = lenient(value, name, &mut problems),
+                "walk" => config.walk = lenient(value, name, &mut problems),
+                "collision" => config.collision = lenient(value, name, &mut problems),
+                _ => problems.push(format!("`{name}` is not a known section")),
+            }
+        }
+
+        if !problems.is_empty() {
+            warn!("Ignored flycam settings:\n  - {}", problems.join("\n  - "));
+        }
+        Ok(config)
+    }
+
+    /// Reads a settings file, falling back to the defaults if it cannot be read or parsed
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) => {
+                warn!("Could not read flycam settings {:?}: {}", path, err);
+                return Self::default();
+            }
+        };
+
+        Self::from_ron(&text).unwrap_or_else(|err| {
+            warn!("Could not parse flycam settings {:?}: {}", path, err);
+            Self::default()
+        })
+    }
+
+    /// Replaces the settings resources with the contents of this file
+    fn insert_resources(self, world: &mut World) {
+        world.insert_resource(self.movement);
+        world.insert_resource(self.bindings);
+        world.insert_resource(self.gamepad);
+        world.insert_resource(self.scroll);
+        world.insert_resource(self.cursor);
+        world.insert_resource(self.orbit);
+        world.insert_resource(self.walk);
+        world.insert_resource(self.collision);
+    }
+}
+
+/// Replaces the flycam settings resources with the contents of a RON settings file
+/// when the app is built, see [`FlyCamConfig`]
+pub struct FlyCamConfigPlugin {
+    pub path: PathBuf,
+}
+
+impl FlyCamConfigPlugin {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Plugin for FlyCamConfigPlugin {
+    fn build(&self, app: &mut App) {
+        FlyCamConfig::load(&self.path).insert_resources(&mut app.world);
+    }
+}
+
+/// Loads [`FlyCamConfig`] assets from `.flycam.ron` files
+#[derive(Default)]
+pub struct FlyCamConfigLoader;
+
+impl AssetLoader for FlyCamConfigLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let config = FlyCamConfig::from_ron(std::str::from_utf8(bytes)?)?;
+            load_context.set_default_asset(LoadedAsset::new(config));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["flycam.ron"]
+    }
+}
+
+/// Loads a `.flycam.ron` asset and copies it into the flycam settings resources every
+/// time it is loaded or modified
+///
+/// `path` is relative to the assets folder, and the file is loaded at startup. Add
+/// this plugin after the `AssetPlugin` (part of `DefaultPlugins`), which registering
+/// the asset type requires. Enable `watch_for_changes` on the `AssetPlugin` to apply
+/// edits to the file while the app runs.
+pub struct FlyCamConfigAssetPlugin {
+    pub path: String,
+}
+
+impl FlyCamConfigAssetPlugin {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Plugin for FlyCamConfigAssetPlugin {
+    fn build(&self, app: &mut App) {
+        let path = self.path.clone();
+        app.add_asset::<FlyCamConfig>()
+            .init_asset_loader::<FlyCamConfigLoader>()
+            .register_type::<FlyCamConfig>()
+            .register_type::<FlyCamConfigHandle>()
+            .init_resource::<FlyCamConfigHandle>()
+            .add_startup_system(
+                move |asset_server: Res<AssetServer>, mut handle: ResMut<FlyCamConfigHandle>| {
+                    handle.0 = asset_server.load(path.as_str());
+                },
+            )
+            .add_system(apply_config);
+    }
+}
+
+/// The settings file loaded by [`FlyCamConfigAssetPlugin`]
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct FlyCamConfigHandle(pub Handle<FlyCamConfig>);
+
+/// Copies the settings file into the resources whenever it is (re)loaded
+fn apply_config(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<FlyCamConfig>>,
+    handle: Res<FlyCamConfigHandle>,
+    configs: Res<Assets<FlyCamConfig>>,
+) {
+    for event in events.iter() {
+        match event {
+            AssetEvent::Created { handle: changed } | AssetEvent::Modified { handle: changed }
+                if *changed == handle.0 =>
+            {
+                if let Some(config) = configs.get(changed) {
+                    info!("Applying flycam settings");
+                    let config = config.clone();
+                    commands.add(move |world: &mut World| config.insert_resources(world));
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Fields of a RON struct, kept as unparsed values in file order
+struct RawFields(Vec<(String, Box<RawValue>)>);
+
+impl<'de> Deserialize<'de> for RawFields {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FieldsVisitor;
+
+        impl<'de> Visitor<'de> for FieldsVisitor {
+            type Value = RawFields;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a struct")
+            }
+
+            fn visit_unit<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+                // `()` is a struct without fields
+                Ok(RawFields(Vec::new()))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut fields = Vec::new();
+                while let Some(field) = map.next_entry()? {
+                    fields.push(field);
+                }
+                Ok(RawFields(fields))
+            }
+        }
+
+        deserializer.deserialize_any(FieldsVisitor)
+    }
+}
+
+/// Parses `T` from fields given as RON text
+fn from_fields<T: DeserializeOwned>(fields: &[(&str, &RawValue)]) -> Result<T, SpannedError> {
+    let body: Vec<String> = fields
+        .iter()
+        .map(|(name, value)| format!("{}: {}", name, value.get_ron()))
+        .collect();
+    ron::from_str(&format!("({})", body.join(", ")))
+}
+
+/// Parses `T` field by field, keeping the default for every field that is missing or
+/// invalid, and describing each of them in `problems`
+fn lenient<T>(value: &RawValue, section: &str, problems: &mut Vec<String>) -> T
+where
+    T: Default + Serialize + DeserializeOwned,
+{
+    let given = match value.into_rust::<RawFields>() {
+        Ok(given) => given.0,
+        Err(err) => {
+            problems.push(format!("`{section}` is invalid ({err}), using defaults"));
+            return T::default();
+        }
+    };
+    let defaults = RawValue::from_rust(&T::default())
+        .and_then(|defaults| defaults.into_rust::<RawFields>().map_err(|err| err.code))
+        .expect("flycam settings should round-trip through RON")
+        .0;
+
+    let mut fields: Vec<(&str, &RawValue)> = defaults
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_ref()))
+        .collect();
+    let missing: Vec<&str> = fields
+        .iter()
+        .map(|(name, _)| *name)
+        .filter(|name| !given.iter().any(|(field, _)| field == name))
+        .collect();
+    if !missing.is_empty() {
+        problems.push(format!(
+            "`{section}` is missing {}, using defaults",
+            missing.join(", ")
+        ));
+    }
+
+    for (name, value) in &given {
+        let Some(index) = fields.iter().position(|(field, _)| field == name) else {
+            problems.push(format!("`{section}.{name}` is not a known field"));
+            continue;
+        };
+
+        let default = std::mem::replace(&mut fields[index].1, value.as_ref());
+        if let Err(err) = from_fields::<T>(&fields) {
+            problems.push(format!(
+                "`{section}.{name}` is invalid ({}), using default",
+                err.code
+            ));
+            fields[index].1 = default;
+        }
+    }
+
+    from_fields(&fields).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_sections_and_fields_use_defaults() {
+        let config = FlyCamConfig::from_ron("(movement: (speed: 20.0))").unwrap();
+        assert_eq!(config.movement.speed, 20.);
+        assert_eq!(
+            config.movement.sensitivity,
+            MovementSettings::default().sensitivity
+        );
+        assert_eq!(config.bindings.forward, KeysBindings::default().forward);
+    }
+
+    #[test]
+    fn every_settings_resource_has_a_section() {
+        let config = FlyCamConfig::from_ron(
+            "(
+                gamepad: (invert_y: true),
+                scroll: (enabled: true),
+                cursor: (mode: Locked, grab_on_startup: false),
+                orbit: (zoom_factor: 1.5),
+                walk: (eye_height: 2.0),
+                collision: (enabled: true),
+            )",
+        )
+        .unwrap();
+        assert!(config.gamepad.invert_y);
+        assert!(config.scroll.enabled);
+        assert_eq!(config.cursor.mode, crate::GrabMode::Locked);
+        assert!(!config.cursor.grab_on_startup);
+        assert_eq!(config.orbit.zoom_factor, 1.5);
+        assert_eq!(config.walk.eye_height, 2.);
+        assert!(config.collision.enabled);
+    }
+
+    #[test]
+    fn unknown_field_is_ignored() {
+        let config = FlyCamConfig::from_ron("(movement: (speed: 20.0, warp: 9.0))").unwrap();
+        assert_eq!(config.movement.speed, 20.);
+    }
+
+    #[test]
+    fn wrongly_typed_field_uses_default() {
+        let config =
+            FlyCamConfig::from_ron(r#"(movement: (speed: "fast", friction: 2.0))"#).unwrap();
+        assert_eq!(config.movement.speed, MovementSettings::default().speed);
+        assert_eq!(config.movement.friction, 2.);
+    }
+
+    #[test]
+    fn unknown_section_is_ignored() {
+        let config =
+            FlyCamConfig::from_ron("(movement: (speed: 20.0), audio: (volume: 1.0))").unwrap();
+        assert_eq!(config.movement.speed, 20.);
+    }
+
+    #[test]
+    fn empty_file_uses_defaults() {
+        for text in ["", "  \n", "()"] {
+            let config = FlyCamConfig::from_ron(text).unwrap();
+            assert_eq!(config.movement.speed, MovementSettings::default().speed);
+        }
+    }
+
+    #[test]
+    fn invalid_ron_is_an_error() {
+        assert!(FlyCamConfig::from_ron("(movement: ").is_err());
+    }
+
+    #[test]
+    fn defaults_round_trip() {
+        let text = ron::to_string(&FlyCamConfig::default()).unwrap();
+        let config = FlyCamConfig::from_ron(&text).unwrap();
+        let defaults = MovementSettings::default();
+        assert_eq!(config.movement.speed, defaults.speed);
+        assert_eq!(config.movement.mode, defaults.mode);
+        assert_eq!(config.movement.bounds, defaults.bounds);
+        assert_eq!(config.movement.bounds_stiffness, f32::INFINITY);
+        assert_eq!(config.bindings.forward, KeysBindings::default().forward);
+        assert_eq!(config.cursor.mode, CursorGrabSettings::default().mode);
+        assert_eq!(config.scroll.max_speed, ScrollSettings::default().max_speed);
+    }
+}
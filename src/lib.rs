@@ -1,82 +1,1069 @@
+//! A basic first-person fly camera for Bevy
+//!
+//! # Per-camera settings
+//!
+//! Settings such as [`MovementSettings`] and [`KeysBindings`] are used as global
+//! resources, and can also be added as components to a [`FlyCam`] to override the
+//! resource for that camera only.
+
 use bevy::ecs::event::{Events, ManualEventReader};
-use bevy::input::mouse::MouseMotion;
+use bevy::ecs::system::SystemParam;
+use bevy::input::keyboard::ScanCode;
+use bevy::input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel};
 use bevy::prelude::*;
-use bevy::window::CursorGrabMode;
+use bevy::render::camera::RenderTarget;
+use bevy::utils::HashMap;
+use bevy::window::{CursorGrabMode, WindowFocused, WindowId};
+
+mod orbit;
+pub use orbit::{OrbitCam, OrbitSettings};
+
+mod walk;
+pub use walk::{FlyCamGround, Heightfield, WalkSettings};
+
+mod collision;
+pub use collision::{CollisionSettings, FlyCamCollider};
+
+mod bounds;
+pub use bounds::{BoundsBox, FlyCamBoundsHit};
 
-/// Keeps track of mouse motion events, pitch, and yaw
-#[derive(Resource, Default)]
-struct InputState {
+#[cfg(feature = "serialize")]
+mod config;
+#[cfg(feature = "serialize")]
+pub use config::{
+    FlyCamConfig, FlyCamConfigAssetPlugin, FlyCamConfigHandle, FlyCamConfigLoader,
+    FlyCamConfigPlugin,
+};
+
+/// Keeps track of mouse motion events, pitch, and yaw for a single [`FlyCam`]
+///
+/// Inserted automatically on every entity with a [`FlyCam`] component, starting from
+/// the orientation of its `Transform`.
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct InputState {
+    #[reflect(ignore)]
     reader_motion: ManualEventReader<MouseMotion>,
-    pitch: f32,
-    yaw: f32,
+    pub pitch: f32,
+    pub yaw: f32,
+    /// Rotation last written by `player_look`, used to detect external changes
+    rotation: Quat,
+    /// Up axis pitch and yaw are measured against
+    up: Vec3,
 }
 
-/// Mouse sensitivity and movement speed
+impl InputState {
+    /// Creates a state whose pitch and yaw around `up` match the given rotation
+    pub fn from_rotation(rotation: Quat, up: Vec3) -> Self {
+        let mut state = Self::default();
+        state.sync_rotation(rotation, up);
+        state
+    }
+
+    /// Re-derives pitch and yaw from `rotation`, discarding any roll
+    fn sync_rotation(&mut self, rotation: Quat, up: Vec3) {
+        let local = Quat::from_rotation_arc(Vec3::Y, up).inverse() * rotation;
+        let (yaw, pitch, _) = local.to_euler(EulerRot::YXZ);
+        self.yaw = yaw;
+        self.pitch = pitch;
+        self.rotation = rotation;
+        self.up = up;
+    }
 
-#[derive(Resource)]
+    /// Re-syncs pitch and yaw if the transform was rotated by something else since
+    /// we last wrote it, or the up axis changed
+    fn sync_if_changed(&mut self, rotation: Quat, up: Vec3) {
+        if rotation != self.rotation || up != self.up {
+            self.sync_rotation(rotation, up);
+        }
+    }
+
+    /// Turns by `pitch` and `yaw` within the pitch limits of `settings`, returning the
+    /// new rotation
+    fn apply_look(&mut self, pitch: f32, yaw: f32, settings: &MovementSettings) -> Quat {
+        self.pitch = clamp_between(self.pitch + pitch, settings.min_pitch, settings.max_pitch);
+        self.yaw += yaw;
+
+        // Order is important to prevent unintended roll
+        self.rotation = Quat::from_rotation_arc(Vec3::Y, self.up)
+            * Quat::from_axis_angle(Vec3::Y, self.yaw)
+            * Quat::from_axis_angle(Vec3::X, self.pitch);
+        self.rotation
+    }
+}
+
+/// Current velocity of a [`FlyCam`], in units per second
+///
+/// Inserted automatically alongside [`InputState`].
+#[derive(Component, Default, Clone, Copy, Debug, Reflect)]
+#[reflect(Component)]
+pub struct FlyCamVelocity(pub Vec3);
+
+/// How the movement keys map to directions in the world
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect, FromReflect)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum MovementMode {
+    /// Forward, back, left and right stay on the horizontal plane, up and down move vertically
+    #[default]
+    Horizontal,
+    /// Forward and back follow the look direction, so looking down and moving forward descends
+    Free,
+    /// Six degrees of freedom: all movement is relative to the camera, rotation
+    /// accumulates in camera-local axes with roll and no pitch limit
+    SixDof,
+}
+
+/// Mouse sensitivity and movement speed
+///
+/// Can be [overridden per camera](crate#per-camera-settings).
+#[derive(Resource, Component, Clone, Reflect)]
+#[reflect(Resource, Component)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct MovementSettings {
     pub sensitivity: f32,
+    pub mode: MovementMode,
+    /// World up direction, used for vertical movement and as the yaw axis
+    ///
+    /// `Vec3::Z` for Z-up scenes. Falls back to `Vec3::Y` if zero.
+    pub up: Vec3,
+    /// Lowest pitch in radians, ignored in [`MovementMode::SixDof`]
+    pub min_pitch: f32,
+    /// Highest pitch in radians, ignored in [`MovementMode::SixDof`]
+    pub max_pitch: f32,
+    /// Roll speed in radians per second, only used in [`MovementMode::SixDof`]
+    pub roll_speed: f32,
+    /// Maximum speed, reached while a movement key is held
     pub speed: f32,
+    /// Speed multiplier while [`KeysBindings::boost`] is held
+    pub boost_multiplier: f32,
+    /// Speed multiplier while [`KeysBindings::slow`] is held
+    pub slow_multiplier: f32,
+    /// How fast the velocity changes towards the pressed direction, in units per second squared
+    ///
+    /// `f32::INFINITY` reaches full speed instantly.
+    pub acceleration: f32,
+    /// Exponential damping applied when no movement key is held, per second
+    ///
+    /// `f32::INFINITY` stops instantly.
+    pub friction: f32,
+    /// Boxes the camera is kept inside of, unbounded when empty
+    ///
+    /// Sends a [`FlyCamBoundsHit`] event when the camera reaches their edge.
+    pub bounds: Vec<BoundsBox>,
+    /// How fast a camera outside of `bounds` is pushed back, per second
+    ///
+    /// `f32::INFINITY` clamps it to the bounds instantly.
+    pub bounds_stiffness: f32,
 }
 
 impl Default for MovementSettings {
     fn default() -> Self {
         Self {
             sensitivity: 0.00012,
+            mode: MovementMode::Horizontal,
+            up: Vec3::Y,
+            min_pitch: -1.54,
+            max_pitch: 1.54,
+            roll_speed: 1.5,
             speed: 12.,
+            boost_multiplier: 3.,
+            slow_multiplier: 0.25,
+            acceleration: 60.,
+            friction: 10.,
+            bounds: vec![],
+            bounds_stiffness: f32::INFINITY,
+        }
+    }
+}
+
+impl MovementSettings {
+    /// Moves at full speed as soon as a key is pressed and stops as soon as it is released
+    pub fn instant() -> Self {
+        Self {
+            acceleration: f32::INFINITY,
+            friction: f32::INFINITY,
+            ..Default::default()
+        }
+    }
+
+    /// Normalized [`MovementSettings::up`]
+    fn up_axis(&self) -> Vec3 {
+        self.up.try_normalize().unwrap_or(Vec3::Y)
+    }
+
+    /// Steps `velocity` towards `target` over `dt` seconds, returning the new velocity
+    /// and the distance moved meanwhile
+    ///
+    /// The distance is integrated exactly rather than per frame, so a camera covers the
+    /// same ground at any frame rate.
+    fn accelerate(&self, velocity: Vec3, target: Vec3, dt: f32) -> (Vec3, Vec3) {
+        let (velocity, moved) = if target != Vec3::ZERO {
+            let delta = target - velocity;
+            let max_delta = self.acceleration * dt;
+            if self.acceleration.is_infinite() || delta == Vec3::ZERO {
+                (target, target * dt)
+            } else if delta.length() <= max_delta {
+                // Reaches the target partway through the step and keeps going at it
+                let reached = delta.length() / self.acceleration;
+                let moved = (velocity + target) * 0.5 * reached + target * (dt - reached);
+                (target, moved)
+            } else {
+                let next = velocity + delta.normalize() * max_delta;
+                (next, (velocity + next) * 0.5 * dt)
+            }
+        } else if self.friction.is_infinite() {
+            (Vec3::ZERO, Vec3::ZERO)
+        } else if self.friction == 0. {
+            (velocity, velocity * dt)
+        } else {
+            let decay = (-self.friction * dt).exp();
+            (velocity * decay, velocity * (1. - decay) / self.friction)
+        };
+
+        // Stop drifting once the camera has practically come to rest
+        if velocity.length_squared() < 1e-6 {
+            (Vec3::ZERO, moved)
+        } else {
+            (velocity, moved)
         }
     }
 }
 
-#[derive(Resource)]
+/// A single input that can trigger a flycam action
+///
+/// Keyboard and mouse bindings only count while the cursor is grabbed. Gamepad
+/// buttons are read from the gamepad selected by [`GamepadControls`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect, FromReflect)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum InputBinding {
+    Key(KeyCode),
+    /// Physical key position, independent of the keyboard layout
+    ScanCode(u32),
+    Mouse(MouseButton),
+    Gamepad(GamepadButtonType),
+}
+
+impl From<KeyCode> for InputBinding {
+    fn from(key: KeyCode) -> Self {
+        Self::Key(key)
+    }
+}
+
+impl From<ScanCode> for InputBinding {
+    fn from(scan_code: ScanCode) -> Self {
+        Self::ScanCode(scan_code.0)
+    }
+}
+
+impl From<MouseButton> for InputBinding {
+    fn from(button: MouseButton) -> Self {
+        Self::Mouse(button)
+    }
+}
+
+impl From<GamepadButtonType> for InputBinding {
+    fn from(button: GamepadButtonType) -> Self {
+        Self::Gamepad(button)
+    }
+}
+
+/// Input bindings for movement and cursor grabbing
+///
+/// Each action is triggered by any of the inputs in its list, and is disabled when
+/// the list is empty.
+///
+/// Can be [overridden per camera](crate#per-camera-settings), except for
+/// `toggle_grab_cursor` and `cycle_flycam` which are always read from the resource.
+#[derive(Resource, Component, Clone, Reflect)]
+#[reflect(Resource, Component)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct KeysBindings {
-    pub forward: KeyCode,
-    pub back: KeyCode,
-    pub right: KeyCode,
-    pub left: KeyCode,
-    pub up: KeyCode,
-    pub down: KeyCode,
-    pub toggle_grab_cursor: KeyCode,
+    pub forward: Vec<InputBinding>,
+    pub back: Vec<InputBinding>,
+    pub right: Vec<InputBinding>,
+    pub left: Vec<InputBinding>,
+    pub up: Vec<InputBinding>,
+    pub down: Vec<InputBinding>,
+    /// Only used in [`MovementMode::SixDof`]
+    pub roll_left: Vec<InputBinding>,
+    /// Only used in [`MovementMode::SixDof`]
+    pub roll_right: Vec<InputBinding>,
+    /// Multiplies the speed by [`MovementSettings::boost_multiplier`] while held
+    pub boost: Vec<InputBinding>,
+    /// Multiplies the speed by [`MovementSettings::slow_multiplier`] while held
+    pub slow: Vec<InputBinding>,
+    /// Makes the mouse wheel zoom instead of changing speed while held, see [`ScrollSettings`]
+    pub scroll_zoom: Vec<InputBinding>,
+    pub toggle_grab_cursor: Vec<InputBinding>,
+    /// Switches control to the next [`FlyCam`]
+    pub cycle_flycam: Vec<InputBinding>,
+    /// Rotates an [`OrbitCam`] around its pivot while held
+    pub orbit_rotate: Vec<InputBinding>,
+    /// Moves the pivot of an [`OrbitCam`] while held
+    pub orbit_pan: Vec<InputBinding>,
+    /// Switches the active flycam to the next [`FlyCamMode`]
+    pub cycle_mode: Vec<InputBinding>,
+    /// Only used in [`FlyCamMode::Walk`]
+    pub jump: Vec<InputBinding>,
 }
 
 impl Default for KeysBindings {
+    fn default() -> Self {
+        Self::from_layout(KeyboardLayout::Qwerty)
+    }
+}
+
+/// Keyboard layouts with built-in [`KeysBindings`] presets
+///
+/// Each preset puts forward, left, back and right on the keys where W, A, S and D
+/// sit on a QWERTY keyboard, with roll and zoom next to them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect, FromReflect)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum KeyboardLayout {
+    /// WASD to move, Q and E to roll, Z to zoom
+    #[default]
+    Qwerty,
+    /// ZQSD to move, A and E to roll, W to zoom
+    Azerty,
+    /// Comma, A, O and E to move, apostrophe and period to roll, semicolon to zoom
+    Dvorak,
+    /// Binds physical key positions with scan codes, so the QWERTY positions work on any layout
+    Physical,
+}
+
+/// Scan codes of the keys used by [`KeyboardLayout::Physical`]
+#[cfg(target_os = "macos")]
+mod scan_code {
+    pub const W: u32 = 13;
+    pub const A: u32 = 0;
+    pub const S: u32 = 1;
+    pub const D: u32 = 2;
+    pub const Q: u32 = 12;
+    pub const E: u32 = 14;
+    pub const Z: u32 = 6;
+}
+
+/// Scan codes of the keys used by [`KeyboardLayout::Physical`]
+#[cfg(not(target_os = "macos"))]
+mod scan_code {
+    pub const W: u32 = 0x11;
+    pub const A: u32 = 0x1e;
+    pub const S: u32 = 0x1f;
+    pub const D: u32 = 0x20;
+    pub const Q: u32 = 0x10;
+    pub const E: u32 = 0x12;
+    pub const Z: u32 = 0x2c;
+}
+
+impl KeysBindings {
+    /// Default bindings for the given keyboard layout
+    pub fn from_layout(layout: KeyboardLayout) -> Self {
+        use InputBinding::{Key, ScanCode};
+        let [forward, left, back, right, roll_left, roll_right, scroll_zoom] = match layout {
+            KeyboardLayout::Qwerty => [
+                KeyCode::W,
+                KeyCode::A,
+                KeyCode::S,
+                KeyCode::D,
+                KeyCode::Q,
+                KeyCode::E,
+                KeyCode::Z,
+            ]
+            .map(Key),
+            KeyboardLayout::Azerty => [
+                KeyCode::Z,
+                KeyCode::Q,
+                KeyCode::S,
+                KeyCode::D,
+                KeyCode::A,
+                KeyCode::E,
+                KeyCode::W,
+            ]
+            .map(Key),
+            KeyboardLayout::Dvorak => [
+                KeyCode::Comma,
+                KeyCode::A,
+                KeyCode::O,
+                KeyCode::E,
+                KeyCode::Apostrophe,
+                KeyCode::Period,
+                KeyCode::Semicolon,
+            ]
+            .map(Key),
+            KeyboardLayout::Physical => [
+                scan_code::W,
+                scan_code::A,
+                scan_code::S,
+                scan_code::D,
+                scan_code::Q,
+                scan_code::E,
+                scan_code::Z,
+            ]
+            .map(ScanCode),
+        };
+
+        Self {
+            forward: vec![forward],
+            back: vec![back],
+            right: vec![right],
+            left: vec![left],
+            up: vec![KeyCode::Space.into()],
+            down: vec![KeyCode::LShift.into()],
+            roll_left: vec![roll_left],
+            roll_right: vec![roll_right],
+            boost: vec![KeyCode::LControl.into()],
+            slow: vec![KeyCode::LAlt.into()],
+            scroll_zoom: vec![scroll_zoom],
+            toggle_grab_cursor: vec![KeyCode::Escape.into()],
+            cycle_flycam: vec![],
+            orbit_rotate: vec![MouseButton::Left.into()],
+            orbit_pan: vec![MouseButton::Middle.into()],
+            cycle_mode: vec![],
+            jump: vec![KeyCode::Space.into(), GamepadButtonType::South.into()],
+        }
+    }
+}
+
+/// Gamepad movement and look settings
+///
+/// The left stick moves, the right stick looks around, and the right and left
+/// triggers or shoulder buttons move up and down. Gamepad input does not require
+/// the cursor to be grabbed.
+///
+/// Can be [overridden per camera](crate#per-camera-settings) to pick a different
+/// gamepad for each camera.
+#[derive(Resource, Component, Clone, Reflect)]
+#[reflect(Resource, Component)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct GamepadControls {
+    pub enabled: bool,
+    /// Gamepad to read from, or the first connected one when `None`
+    pub gamepad: Option<Gamepad>,
+    /// Stick deflection below which input is ignored, between 0 and 1
+    pub deadzone: f32,
+    /// Response curve exponent applied to stick deflection past the deadzone
+    ///
+    /// `1.0` is linear, higher values give finer control near the center.
+    pub response_exponent: f32,
+    /// Look speed at full right stick deflection, in radians per second
+    pub look_sensitivity: f32,
+    pub invert_y: bool,
+}
+
+impl Default for GamepadControls {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            gamepad: None,
+            deadzone: 0.1,
+            response_exponent: 2.,
+            look_sensitivity: 2.5,
+            invert_y: false,
+        }
+    }
+}
+
+/// Mouse wheel control of the active flycam's speed and zoom
+///
+/// Scrolling scales [`MovementSettings::speed`] by `speed_factor` per line. While
+/// [`KeysBindings::scroll_zoom`] is held it scales the perspective `fov` or the
+/// orthographic `scale` by `zoom_factor` per line instead.
+#[derive(Resource, Clone, Reflect)]
+#[reflect(Resource)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScrollSettings {
+    pub enabled: bool,
+    pub speed_factor: f32,
+    pub min_speed: f32,
+    pub max_speed: f32,
+    pub zoom_factor: f32,
+    /// Field of view limits in radians
+    pub min_fov: f32,
+    pub max_fov: f32,
+    pub min_scale: f32,
+    pub max_scale: f32,
+}
+
+impl Default for ScrollSettings {
     fn default() -> Self {
         Self {
-            forward: KeyCode::W,
-            back: KeyCode::S,
-            right: KeyCode::D,
-            left: KeyCode::A,
-            up: KeyCode::Space,
-            down: KeyCode::LShift,
-            toggle_grab_cursor: KeyCode::Escape,
+            enabled: false,
+            speed_factor: 1.1,
+            min_speed: 0.1,
+            max_speed: 1000.,
+            zoom_factor: 1.05,
+            min_fov: 0.05,
+            max_fov: 2.5,
+            min_scale: 0.01,
+            max_scale: 100.,
         }
     }
 }
 
+/// How the cursor is held while grabbed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect, FromReflect)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum GrabMode {
+    /// The cursor can move but stays inside the window
+    #[default]
+    Confined,
+    /// The cursor stays in place, not supported on every platform
+    Locked,
+}
+
+impl From<GrabMode> for CursorGrabMode {
+    fn from(mode: GrabMode) -> Self {
+        match mode {
+            GrabMode::Confined => CursorGrabMode::Confined,
+            GrabMode::Locked => CursorGrabMode::Locked,
+        }
+    }
+}
+
+/// When the cursor is grabbed and released
+///
+/// The cursor is hidden while grabbed and shown again whenever it is released.
+#[derive(Resource, Clone, Reflect)]
+#[reflect(Resource)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct CursorGrabSettings {
+    pub mode: GrabMode,
+    /// Grab the cursor when the app starts, or once the window is first focused
+    pub grab_on_startup: bool,
+    /// Grab the cursor when the left mouse button is clicked in the window
+    pub grab_on_click: bool,
+    /// Release the cursor when the window loses focus
+    pub release_on_focus_loss: bool,
+    /// Grab the cursor only while this button is held, editor style
+    ///
+    /// Looking and moving with the mouse and keyboard only work while the cursor is
+    /// grabbed, so the cursor stays free for UI the rest of the time.
+    pub hold_to_grab: Option<MouseButton>,
+}
+
+impl Default for CursorGrabSettings {
+    fn default() -> Self {
+        Self {
+            mode: GrabMode::Confined,
+            grab_on_startup: true,
+            grab_on_click: false,
+            release_on_focus_loss: true,
+            hold_to_grab: None,
+        }
+    }
+}
+
+impl CursorGrabSettings {
+    /// Looks and moves only while `button` is held, leaving the cursor free otherwise
+    pub fn hold_to_look(button: MouseButton) -> Self {
+        Self {
+            mode: GrabMode::Locked,
+            grab_on_startup: false,
+            hold_to_grab: Some(button),
+            ..Default::default()
+        }
+    }
+}
+
+/// Suppresses flycam movement, look, scroll and grab input while set
+///
+/// Blocked input reads as released, so a moving camera keeps coasting, falling or
+/// stopping as usual. Releasing the cursor and [`SwitchFlyCam`] events still work.
+///
+/// `manual` is left to the app, e.g. while a text field is focused. With the
+/// `bevy_ui` feature, `ui` is set automatically while the free cursor hovers or
+/// presses a UI node.
+#[derive(Resource, Default, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct FlyCamInputBlocked {
+    pub manual: bool,
+    pub ui: bool,
+}
+
+impl FlyCamInputBlocked {
+    pub fn is_blocked(&self) -> bool {
+        self.manual || self.ui
+    }
+}
+
 /// A marker component used in queries when you want flycams and not other cameras
-#[derive(Component)]
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
 pub struct FlyCam;
 
-/// Grabs/ungrabs mouse cursor
-fn toggle_grab_cursor(window: &mut Window) {
-    match window.cursor_grab_mode() {
-        CursorGrabMode::None => {
-            window.set_cursor_grab_mode(CursorGrabMode::Confined);
-            window.set_cursor_visibility(false);
+/// Which controller drives a [`FlyCam`]
+///
+/// Inserted automatically, matching whether the flycam already has an [`OrbitCam`].
+/// Changing it hands over from the current pose so the camera does not jump:
+/// orbiting starts around the point [`OrbitSettings::pivot_distance`] ahead of the
+/// camera, and flying and walking continue from the orbit pose.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect, FromReflect)]
+#[reflect(Component)]
+pub enum FlyCamMode {
+    #[default]
+    Fly,
+    Orbit,
+    /// Walks on [`FlyCamGround`] at eye height with gravity, see [`WalkSettings`]
+    Walk,
+}
+
+impl FlyCamMode {
+    /// The mode [`KeysBindings::cycle_mode`] switches to
+    pub fn next(self) -> Self {
+        match self {
+            FlyCamMode::Fly => FlyCamMode::Orbit,
+            FlyCamMode::Orbit => FlyCamMode::Walk,
+            FlyCamMode::Walk => FlyCamMode::Fly,
+        }
+    }
+}
+
+/// A marker component for the [`FlyCam`] that currently receives input in its window
+///
+/// Every window has its own active flycam, and the first one found is made active
+/// if there is none. Its `Camera` is the only flycam camera left active on that
+/// window, except for cameras with a `viewport`, which keep rendering so split views
+/// work. Input only reaches it while its window is focused.
+///
+/// `Camera::is_active` is only changed when a flycam is spawned or the active one
+/// changes, so it can still be turned off in between, e.g. during a cutscene.
+///
+/// Flycams without a `Camera` or rendering to an image are never active.
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct ActiveFlyCam;
+
+/// Send this event to change which [`FlyCam`] receives input
+///
+/// `Next` and `Previous` cycle the flycams of the focused window in [`Entity`] order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Reflect, FromReflect)]
+pub enum SwitchFlyCam {
+    Next,
+    Previous,
+    To(Entity),
+}
+
+/// Keyboard, mouse and gamepad resources read by the flycam systems
+#[derive(SystemParam)]
+struct FlyCamInput<'w, 's> {
+    keys: Res<'w, Input<KeyCode>>,
+    scan_codes: Res<'w, Input<ScanCode>>,
+    mouse_buttons: Res<'w, Input<MouseButton>>,
+    gamepads: Res<'w, Gamepads>,
+    axes: Res<'w, Axis<GamepadAxis>>,
+    button_axes: Res<'w, Axis<GamepadButton>>,
+    buttons: Res<'w, Input<GamepadButton>>,
+    controls: Res<'w, GamepadControls>,
+    blocked: Res<'w, FlyCamInputBlocked>,
+    #[system_param(ignore)]
+    _marker: std::marker::PhantomData<&'s ()>,
+}
+
+impl FlyCamInput<'_, '_> {
+    /// Whether [`FlyCamInputBlocked`] is set, in which case every input reads as released
+    fn is_blocked(&self) -> bool {
+        self.blocked.is_blocked()
+    }
+
+    /// Whether any of `bindings` is held
+    ///
+    /// Keyboard and mouse bindings are ignored unless `grabbed` is set.
+    fn pressed(&self, bindings: &[InputBinding], gamepad: Option<Gamepad>, grabbed: bool) -> bool {
+        if self.is_blocked() {
+            return false;
         }
-        _ => {
-            window.set_cursor_grab_mode(CursorGrabMode::None);
-            window.set_cursor_visibility(true);
+        bindings.iter().any(|binding| match *binding {
+            InputBinding::Key(key) => grabbed && self.keys.pressed(key),
+            InputBinding::ScanCode(code) => grabbed && self.scan_codes.pressed(ScanCode(code)),
+            InputBinding::Mouse(button) => grabbed && self.mouse_buttons.pressed(button),
+            InputBinding::Gamepad(button) => gamepad
+                .is_some_and(|gamepad| self.buttons.pressed(GamepadButton::new(gamepad, button))),
+        })
+    }
+
+    /// Whether any of `bindings` was pressed this frame
+    fn just_pressed(&self, bindings: &[InputBinding], gamepad: Option<Gamepad>) -> bool {
+        if self.is_blocked() {
+            return false;
         }
+        bindings.iter().any(|binding| match *binding {
+            InputBinding::Key(key) => self.keys.just_pressed(key),
+            InputBinding::ScanCode(code) => self.scan_codes.just_pressed(ScanCode(code)),
+            InputBinding::Mouse(button) => self.mouse_buttons.just_pressed(button),
+            InputBinding::Gamepad(button) => gamepad.is_some_and(|gamepad| {
+                self.buttons
+                    .just_pressed(GamepadButton::new(gamepad, button))
+            }),
+        })
     }
+
+    /// The gamepad selected by the global [`GamepadControls`]
+    fn default_gamepad(&self) -> Option<Gamepad> {
+        self.gamepad(&self.controls)
+    }
+
+    /// Controls for a flycam, preferring its own component over the resource
+    fn controls<'a>(&'a self, cam_controls: Option<&'a GamepadControls>) -> &'a GamepadControls {
+        cam_controls.unwrap_or(&self.controls)
+    }
+
+    /// The gamepad driving a flycam with these controls, if any is connected
+    fn gamepad(&self, controls: &GamepadControls) -> Option<Gamepad> {
+        if !controls.enabled {
+            return None;
+        }
+        match controls.gamepad {
+            Some(gamepad) => self.gamepads.contains(gamepad).then_some(gamepad),
+            None => self.gamepads.iter().next(),
+        }
+    }
+
+    /// Stick position with the radial deadzone and response curve applied
+    fn stick(
+        &self,
+        gamepad: Gamepad,
+        x: GamepadAxisType,
+        y: GamepadAxisType,
+        controls: &GamepadControls,
+    ) -> Vec2 {
+        if self.is_blocked() {
+            return Vec2::ZERO;
+        }
+        let raw = Vec2::new(
+            self.axes.get(GamepadAxis::new(gamepad, x)).unwrap_or(0.),
+            self.axes.get(GamepadAxis::new(gamepad, y)).unwrap_or(0.),
+        );
+        let magnitude = raw.length();
+        if magnitude <= controls.deadzone {
+            return Vec2::ZERO;
+        }
+
+        let scaled = ((magnitude - controls.deadzone) / (1. - controls.deadzone)).min(1.);
+        raw / magnitude * scaled.powf(controls.response_exponent)
+    }
+
+    /// Pitch and yaw in radians from the right stick over `dt` seconds
+    fn look_stick(
+        &self,
+        gamepad: Option<Gamepad>,
+        controls: &GamepadControls,
+        dt: f32,
+    ) -> (f32, f32) {
+        let Some(gamepad) = gamepad else {
+            return (0., 0.);
+        };
+        let stick = self.stick(
+            gamepad,
+            GamepadAxisType::RightStickX,
+            GamepadAxisType::RightStickY,
+            controls,
+        ) * controls.look_sensitivity
+            * dt;
+        let pitch = if controls.invert_y { -stick.y } else { stick.y };
+        (pitch, -stick.x)
+    }
+
+    /// How far a trigger is pulled, or 1 if its digital button is pressed
+    fn trigger(
+        &self,
+        gamepad: Gamepad,
+        analog: GamepadButtonType,
+        digital: GamepadButtonType,
+    ) -> f32 {
+        if self.is_blocked() {
+            return 0.;
+        }
+        let pressed = self.buttons.pressed(GamepadButton::new(gamepad, digital));
+        let value = self
+            .button_axes
+            .get(GamepadButton::new(gamepad, analog))
+            .unwrap_or(0.);
+        if pressed {
+            1.
+        } else {
+            value
+        }
+    }
+}
+
+/// Clamps `value` between `a` and `b` in either order
+///
+/// Unlike `f32::clamp` this does not panic on reversed or NaN limits, which can come
+/// from a settings file. A NaN limit is ignored.
+fn clamp_between(value: f32, a: f32, b: f32) -> f32 {
+    // `a > b` is false for NaN, which `f32::max` and `f32::min` then skip
+    let (min, max) = if a > b { (b, a) } else { (a, b) };
+    value.max(min).min(max)
+}
+
+/// The window a flycam reads input from, which is the one its camera renders to
+///
+/// Flycams without a camera or rendering to an image have no window.
+fn flycam_window(camera: Option<&Camera>) -> Option<WindowId> {
+    match camera.map(|camera| &camera.target) {
+        Some(RenderTarget::Window(id)) => Some(*id),
+        _ => None,
+    }
+}
+
+/// The window of a flycam, if it exists and is focused
+fn focused_window<'a>(windows: &'a Windows, camera: Option<&Camera>) -> Option<&'a Window> {
+    flycam_window(camera)
+        .and_then(|id| windows.get(id))
+        .filter(|window| window.is_focused())
 }
 
-/// Grabs the cursor when game first starts
-fn initial_grab_cursor(mut windows: ResMut<Windows>) {
-    if let Some(window) = windows.get_primary_mut() {
-        toggle_grab_cursor(window);
-    } else {
-        warn!("Primary window not found for `initial_grab_cursor`!");
+/// Adds an [`InputState`] and [`FlyCamVelocity`] to every [`FlyCam`] that does not have one
+/// yet, and a [`FlyCamMode`] if it is missing
+#[allow(clippy::type_complexity)]
+fn setup_input_state(
+    mut commands: Commands,
+    settings: Res<MovementSettings>,
+    query: Query<
+        (
+            Entity,
+            &Transform,
+            Option<&MovementSettings>,
+            Option<&FlyCamMode>,
+            Option<&OrbitCam>,
+        ),
+        (With<FlyCam>, Without<InputState>),
+    >,
+) {
+    for (entity, transform, cam_settings, mode, orbit) in query.iter() {
+        let settings = cam_settings.unwrap_or(&settings);
+        let mut entity = commands.entity(entity);
+        entity.insert((
+            InputState::from_rotation(transform.rotation, settings.up_axis()),
+            FlyCamVelocity::default(),
+        ));
+        if mode.is_none() {
+            entity.insert(match orbit {
+                Some(_) => FlyCamMode::Orbit,
+                None => FlyCamMode::Fly,
+            });
+        }
+    }
+}
+
+/// Switches the active flycam to the next [`FlyCamMode`] with the `cycle_mode` binding
+#[allow(clippy::type_complexity)]
+fn cycle_flycam_mode(
+    input: FlyCamInput,
+    windows: Res<Windows>,
+    key_bindings: Res<KeysBindings>,
+    mut query: Query<
+        (
+            &mut FlyCamMode,
+            Option<&KeysBindings>,
+            Option<&GamepadControls>,
+            Option<&Camera>,
+        ),
+        (With<FlyCam>, With<ActiveFlyCam>),
+    >,
+) {
+    for (mut mode, cam_bindings, cam_controls, camera) in query.iter_mut() {
+        if focused_window(&windows, camera).is_none() {
+            continue;
+        }
+        let key_bindings = cam_bindings.unwrap_or(&key_bindings);
+        let gamepad = input.gamepad(input.controls(cam_controls));
+        if input.just_pressed(&key_bindings.cycle_mode, gamepad) {
+            *mode = mode.next();
+        }
+    }
+}
+
+/// Hands a flycam over to the controller selected by its [`FlyCamMode`]
+#[allow(clippy::type_complexity)]
+fn apply_flycam_mode(
+    mut commands: Commands,
+    orbit_settings: Res<OrbitSettings>,
+    mut query: Query<
+        (
+            Entity,
+            &FlyCamMode,
+            &Transform,
+            Option<&mut FlyCamVelocity>,
+            Option<&OrbitCam>,
+            Option<&OrbitSettings>,
+        ),
+        (With<FlyCam>, Changed<FlyCamMode>),
+    >,
+) {
+    for (entity, mode, transform, velocity, orbit, cam_orbit_settings) in query.iter_mut() {
+        match mode {
+            FlyCamMode::Fly | FlyCamMode::Walk if orbit.is_some() => {
+                commands.entity(entity).remove::<OrbitCam>();
+            }
+            FlyCamMode::Orbit if orbit.is_none() => {
+                let orbit_settings = cam_orbit_settings.unwrap_or(&orbit_settings);
+                commands.entity(entity).insert(OrbitCam::in_front_of(
+                    transform,
+                    orbit_settings.pivot_distance,
+                ));
+                // Do not keep drifting when flying again
+                if let Some(mut velocity) = velocity {
+                    velocity.0 = Vec3::ZERO;
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Moves the [`ActiveFlyCam`] marker of each window in response to [`SwitchFlyCam`]
+/// events and the `cycle_flycam` key, updating `Camera::is_active` when it moves
+#[allow(clippy::type_complexity)]
+fn switch_flycam(
+    mut commands: Commands,
+    input: FlyCamInput,
+    key_bindings: Res<KeysBindings>,
+    windows: Res<Windows>,
+    mut events: EventReader<SwitchFlyCam>,
+    mut query: Query<
+        (
+            Entity,
+            Option<&mut Camera>,
+            Option<&ActiveFlyCam>,
+            ChangeTrackers<FlyCam>,
+        ),
+        With<FlyCam>,
+    >,
+) {
+    let mut flycams: HashMap<WindowId, Vec<Entity>> = HashMap::default();
+    for (entity, camera, ..) in query.iter() {
+        if let Some(window) = flycam_window(camera) {
+            flycams.entry(window).or_default().push(entity);
+        }
+    }
+    if flycams.is_empty() {
+        events.clear();
+        return;
+    }
+
+    let mut targets: HashMap<WindowId, Entity> = HashMap::default();
+    for (window, entities) in flycams.iter_mut() {
+        entities.sort();
+        let current = entities.iter().copied().find(|&entity| {
+            query
+                .get(entity)
+                .is_ok_and(|(_, _, active, _)| active.is_some())
+        });
+        targets.insert(*window, current.unwrap_or(entities[0]));
+    }
+
+    let focused = windows
+        .iter()
+        .find(|window| window.is_focused())
+        .map_or_else(WindowId::primary, |window| window.id());
+    let cycle_pressed = input.just_pressed(&key_bindings.cycle_flycam, input.default_gamepad());
+    let requests = events
+        .iter()
+        .copied()
+        .chain(cycle_pressed.then_some(SwitchFlyCam::Next));
+    for request in requests {
+        let window = match request {
+            SwitchFlyCam::To(entity) => {
+                match flycams
+                    .iter()
+                    .find(|(_, entities)| entities.contains(&entity))
+                {
+                    Some((window, _)) => *window,
+                    None => {
+                        warn!(
+                            "{:?} is not a `FlyCam` rendering to a window, ignoring `SwitchFlyCam`",
+                            entity
+                        );
+                        continue;
+                    }
+                }
+            }
+            _ => focused,
+        };
+        // The focused window has no flycam to cycle through
+        let entities = match flycams.get(&window) {
+            Some(entities) => entities,
+            None => continue,
+        };
+
+        let target = targets[&window];
+        let index = entities.iter().position(|&e| e == target).unwrap_or(0);
+        let target = match request {
+            SwitchFlyCam::Next => entities[(index + 1) % entities.len()],
+            SwitchFlyCam::Previous => entities[(index + entities.len() - 1) % entities.len()],
+            SwitchFlyCam::To(entity) => entity,
+        };
+        targets.insert(window, target);
+    }
+
+    for (entity, camera, active, tracker) in query.iter_mut() {
+        let window = flycam_window(camera.as_deref());
+        let is_target = window.is_some_and(|window| targets[&window] == entity);
+        let switched = is_target != active.is_some();
+        if switched && is_target {
+            commands.entity(entity).insert(ActiveFlyCam);
+        } else if switched {
+            commands.entity(entity).remove::<ActiveFlyCam>();
+        }
+
+        // Only touch `is_active` on a switch, so the app can still turn the camera off.
+        // Viewports share the window with other cameras, so they keep rendering.
+        if let Some(mut camera) = camera {
+            if (switched || tracker.is_added())
+                && window.is_some()
+                && camera.viewport.is_none()
+                && camera.is_active != is_target
+            {
+                camera.is_active = is_target;
+            }
+        }
+    }
+}
+
+/// Grabs and hides mouse cursor
+fn grab_cursor(window: &mut Window, mode: GrabMode) {
+    window.set_cursor_grab_mode(mode.into());
+    window.set_cursor_visibility(false);
+}
+
+/// Releases and shows mouse cursor
+fn release_cursor(window: &mut Window) {
+    window.set_cursor_grab_mode(CursorGrabMode::None);
+    window.set_cursor_visibility(true);
+}
+
+/// Grabs/ungrabs mouse cursor
+fn toggle_grab_cursor(window: &mut Window, mode: GrabMode) {
+    match window.cursor_grab_mode() {
+        CursorGrabMode::None => grab_cursor(window, mode),
+        _ => release_cursor(window),
+    }
+}
+
+/// Grabs the cursor of each window the first time a flycam renders to it while focused
+fn initial_grab_cursor(
+    grab_settings: Res<CursorGrabSettings>,
+    mut windows: ResMut<Windows>,
+    mut grabbed: Local<Vec<WindowId>>,
+    query: Query<Option<&Camera>, With<FlyCam>>,
+) {
+    if !grab_settings.grab_on_startup {
+        return;
+    }
+
+    for id in query.iter().filter_map(flycam_window) {
+        if grabbed.contains(&id) {
+            continue;
+        }
+        // Unfocused windows are grabbed once they gain focus
+        match windows.get_mut(id) {
+            Some(window) if window.is_focused() => {
+                grab_cursor(window, grab_settings.mode);
+                grabbed.push(id);
+            }
+            _ => {}
+        }
     }
 }
 
@@ -92,92 +1079,395 @@ fn setup_player(mut commands: Commands) {
 }
 
 /// Handles keyboard input and movement
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
 fn player_move(
-    keys: Res<Input<KeyCode>>,
     time: Res<Time>,
     windows: Res<Windows>,
     settings: Res<MovementSettings>,
+    walk_settings: Res<WalkSettings>,
+    ground: Res<FlyCamGround>,
+    collision: Res<CollisionSettings>,
     key_bindings: Res<KeysBindings>,
-    mut query: Query<&mut Transform, With<FlyCam>>,
+    input: FlyCamInput,
+    mut query: Query<
+        (
+            &mut Transform,
+            &mut FlyCamVelocity,
+            Option<&FlyCamMode>,
+            Option<&MovementSettings>,
+            Option<&WalkSettings>,
+            Option<&CollisionSettings>,
+            Option<&KeysBindings>,
+            Option<&GamepadControls>,
+            Option<&Camera>,
+        ),
+        (With<FlyCam>, With<ActiveFlyCam>, Without<OrbitCam>),
+    >,
+    colliders: Query<(&FlyCamCollider, &GlobalTransform), Without<ActiveFlyCam>>,
 ) {
-    if let Some(window) = windows.get_primary() {
-        for mut transform in query.iter_mut() {
-            let mut velocity = Vec3::ZERO;
-            let local_z = transform.local_z();
-            let forward = -Vec3::new(local_z.x, 0., local_z.z);
-            let right = Vec3::new(local_z.z, 0., -local_z.x);
-
-            for key in keys.get_pressed() {
-                match window.cursor_grab_mode() {
-                    CursorGrabMode::None => (),
-                    _ => match key {
-                        k if k == &key_bindings.forward => velocity += forward,
-                        k if k == &key_bindings.back => velocity -= forward,
-                        k if k == &key_bindings.left => velocity -= right,
-                        k if k == &key_bindings.right => velocity += right,
-                        k if k == &key_bindings.up => velocity += Vec3::Y,
-                        k if k == &key_bindings.down => velocity -= Vec3::Y,
-                        _ => (),
-                    },
-                }
+    for (
+        mut transform,
+        mut velocity,
+        mode,
+        cam_settings,
+        cam_walk_settings,
+        cam_collision,
+        cam_bindings,
+        cam_controls,
+        camera,
+    ) in query.iter_mut()
+    {
+        let window = match focused_window(&windows, camera) {
+            Some(window) => window,
+            None => continue,
+        };
+        let grabbed = window.cursor_grab_mode() != CursorGrabMode::None;
+        let settings = cam_settings.unwrap_or(&settings);
+        let key_bindings = cam_bindings.unwrap_or(&key_bindings);
+        let controls = input.controls(cam_controls);
+        let gamepad = input.gamepad(controls);
+        let pressed = |bindings: &[InputBinding]| input.pressed(bindings, gamepad, grabbed);
+        let walk_settings = cam_walk_settings.unwrap_or(&walk_settings);
+        let walking = mode == Some(&FlyCamMode::Walk);
+        let mut direction = Vec3::ZERO;
+        let mut speed = if walking {
+            walk_settings.speed
+        } else {
+            settings.speed
+        };
+        let local_z = transform.local_z();
+        let world_up = settings.up_axis();
+        let (forward, right, up) = match settings.mode {
+            // Walking stays on the ground, so up and down do nothing
+            _ if walking => {
+                let forward = -local_z + world_up * local_z.dot(world_up);
+                (forward, forward.cross(world_up), Vec3::ZERO)
+            }
+            MovementMode::Horizontal => {
+                // Look direction projected onto the plane perpendicular to `world_up`
+                let forward = -local_z + world_up * local_z.dot(world_up);
+                (forward, forward.cross(world_up), world_up)
             }
+            MovementMode::Free => (-local_z, transform.local_x(), world_up),
+            MovementMode::SixDof => (-local_z, transform.local_x(), transform.local_y()),
+        };
 
-            velocity = velocity.normalize_or_zero();
+        if pressed(&key_bindings.forward) {
+            direction += forward;
+        }
+        if pressed(&key_bindings.back) {
+            direction -= forward;
+        }
+        if pressed(&key_bindings.left) {
+            direction -= right;
+        }
+        if pressed(&key_bindings.right) {
+            direction += right;
+        }
+        if pressed(&key_bindings.up) {
+            direction += up;
+        }
+        if pressed(&key_bindings.down) {
+            direction -= up;
+        }
+        if pressed(&key_bindings.boost) {
+            speed *= settings.boost_multiplier;
+        }
+        if pressed(&key_bindings.slow) {
+            speed *= settings.slow_multiplier;
+        }
+
+        let mut analog = Vec3::ZERO;
+        if let Some(gamepad) = gamepad {
+            let stick = input.stick(
+                gamepad,
+                GamepadAxisType::LeftStickX,
+                GamepadAxisType::LeftStickY,
+                controls,
+            );
+            let vertical = input.trigger(
+                gamepad,
+                GamepadButtonType::RightTrigger2,
+                GamepadButtonType::RightTrigger,
+            ) - input.trigger(
+                gamepad,
+                GamepadButtonType::LeftTrigger2,
+                GamepadButtonType::LeftTrigger,
+            );
+            analog = forward.normalize_or_zero() * stick.y
+                + right.normalize_or_zero() * stick.x
+                + up * vertical;
+        }
+
+        let target = (direction.normalize_or_zero() + analog).clamp_length_max(1.) * speed;
+        let dt = time.delta_seconds();
+        let start = transform.translation;
+        let mut translation = start;
+        if walking {
+            walk::walk(
+                &mut velocity.0,
+                &mut translation,
+                target,
+                pressed(&key_bindings.jump),
+                settings,
+                walk_settings,
+                &ground,
+                dt,
+            );
+        } else {
+            let moved;
+            (velocity.0, moved) = settings.accelerate(velocity.0, target, dt);
+            translation += moved;
+        }
+
+        let collision = cam_collision.unwrap_or(&collision);
+        if collision.enabled {
+            let delta = translation - start;
+            translation = start;
+            collision::move_and_slide(
+                &mut translation,
+                &mut velocity.0,
+                delta,
+                collision,
+                &colliders.iter().collect::<Vec<_>>(),
+            );
+        }
 
-            transform.translation += velocity * time.delta_seconds() * settings.speed
+        if translation != start {
+            transform.translation = translation;
         }
-    } else {
-        warn!("Primary window not found for `player_move`!");
     }
 }
 
 /// Handles looking around if cursor is locked
+#[allow(clippy::type_complexity)]
 fn player_look(
+    time: Res<Time>,
     settings: Res<MovementSettings>,
+    key_bindings: Res<KeysBindings>,
     windows: Res<Windows>,
-    mut state: ResMut<InputState>,
     motion: Res<Events<MouseMotion>>,
-    mut query: Query<&mut Transform, With<FlyCam>>,
+    input: FlyCamInput,
+    mut query: Query<
+        (
+            &mut InputState,
+            &mut Transform,
+            Option<&MovementSettings>,
+            Option<&KeysBindings>,
+            Option<&GamepadControls>,
+            Option<&Camera>,
+            Option<&FlyCamMode>,
+        ),
+        (With<FlyCam>, With<ActiveFlyCam>, Without<OrbitCam>),
+    >,
 ) {
-    if let Some(window) = windows.get_primary() {
-        let mut delta_state = state.as_mut();
-        for mut transform in query.iter_mut() {
-            for ev in delta_state.reader_motion.iter(&motion) {
-                match window.cursor_grab_mode() {
-                    CursorGrabMode::None => (),
-                    _ => {
-                        // Using smallest of height or width ensures equal vertical and horizontal sensitivity
-                        let window_scale = window.height().min(window.width());
-                        delta_state.pitch -=
-                            (settings.sensitivity * ev.delta.y * window_scale).to_radians();
-                        delta_state.yaw -=
-                            (settings.sensitivity * ev.delta.x * window_scale).to_radians();
-                    }
-                }
+    for (mut state, mut transform, cam_settings, cam_bindings, cam_controls, camera, mode) in
+        query.iter_mut()
+    {
+        let window = match focused_window(&windows, camera) {
+            Some(window) => window,
+            None => continue,
+        };
+        let grabbed = window.cursor_grab_mode() != CursorGrabMode::None;
+        let settings = cam_settings.unwrap_or(&settings);
+        let key_bindings = cam_bindings.unwrap_or(&key_bindings);
+        let delta_state = state.as_mut();
+        let up = settings.up_axis();
+        delta_state.sync_if_changed(transform.rotation, up);
 
-                delta_state.pitch = delta_state.pitch.clamp(-1.54, 1.54);
+        let mut delta = Vec2::ZERO;
+        for ev in delta_state.reader_motion.iter(&motion) {
+            if grabbed && !input.is_blocked() {
+                delta += ev.delta;
+            }
+        }
+
+        // Using smallest of height or width ensures equal vertical and horizontal sensitivity
+        let window_scale = window.height().min(window.width());
+        let controls = input.controls(cam_controls);
+        let gamepad = input.gamepad(controls);
+        let (stick_pitch, stick_yaw) = input.look_stick(gamepad, controls, time.delta_seconds());
+        let pitch = stick_pitch - (settings.sensitivity * delta.y * window_scale).to_radians();
+        let yaw = stick_yaw - (settings.sensitivity * delta.x * window_scale).to_radians();
+
+        // Walking always keeps the camera upright
+        if settings.mode == MovementMode::SixDof && mode != Some(&FlyCamMode::Walk) {
+            let mut roll = 0.;
+            if input.pressed(&key_bindings.roll_left, gamepad, grabbed) {
+                roll += settings.roll_speed * time.delta_seconds();
+            }
+            if input.pressed(&key_bindings.roll_right, gamepad, grabbed) {
+                roll -= settings.roll_speed * time.delta_seconds();
+            }
 
-                // Order is important to prevent unintended roll
-                transform.rotation = Quat::from_axis_angle(Vec3::Y, delta_state.yaw)
-                    * Quat::from_axis_angle(Vec3::X, delta_state.pitch);
+            if pitch != 0. || yaw != 0. || roll != 0. {
+                // Rotations are applied in camera-local axes, so there is no gimbal to clamp
+                transform.rotation = (transform.rotation
+                    * Quat::from_rotation_y(yaw)
+                    * Quat::from_rotation_x(pitch)
+                    * Quat::from_rotation_z(roll))
+                .normalize();
+                delta_state.sync_rotation(transform.rotation, up);
             }
+        } else if pitch != 0. || yaw != 0. {
+            transform.rotation = delta_state.apply_look(pitch, yaw, settings);
         }
-    } else {
-        warn!("Primary window not found for `player_look`!");
     }
 }
 
+/// Number of pixels treated as one line for touchpads reporting [`MouseScrollUnit::Pixel`]
+const PIXELS_PER_LINE: f32 = 20.;
+
+/// Number of lines scrolled by all pending wheel events, positive when scrolling up
+fn wheel_lines(wheel: &mut EventReader<MouseWheel>) -> f32 {
+    wheel
+        .iter()
+        .map(|ev| match ev.unit {
+            MouseScrollUnit::Line => ev.y,
+            MouseScrollUnit::Pixel => ev.y / PIXELS_PER_LINE,
+        })
+        .sum()
+}
+
+/// Changes speed or zoom of the active flycam with the mouse wheel if cursor is locked
+#[allow(clippy::type_complexity)]
+fn scroll(
+    mut wheel: EventReader<MouseWheel>,
+    input: FlyCamInput,
+    windows: Res<Windows>,
+    scroll_settings: Res<ScrollSettings>,
+    key_bindings: Res<KeysBindings>,
+    mut settings: ResMut<MovementSettings>,
+    mut query: Query<
+        (
+            Option<&mut MovementSettings>,
+            Option<&KeysBindings>,
+            Option<&GamepadControls>,
+            Option<&mut Projection>,
+            Option<&Camera>,
+        ),
+        (With<FlyCam>, With<ActiveFlyCam>, Without<OrbitCam>),
+    >,
+) {
+    let lines = wheel_lines(&mut wheel);
+    if !scroll_settings.enabled || lines == 0. || input.is_blocked() {
+        return;
+    }
+
+    for (cam_settings, cam_bindings, cam_controls, projection, camera) in query.iter_mut() {
+        let grabbed = focused_window(&windows, camera)
+            .is_some_and(|window| window.cursor_grab_mode() != CursorGrabMode::None);
+        if !grabbed {
+            continue;
+        }
+
+        let key_bindings = cam_bindings.unwrap_or(&key_bindings);
+        let gamepad = input.gamepad(input.controls(cam_controls));
+        if input.pressed(&key_bindings.scroll_zoom, gamepad, true) {
+            // Scrolling up zooms in, which narrows the view
+            let zoom = scroll_settings.zoom_factor.powf(-lines);
+            match projection.map(|p| p.into_inner()) {
+                Some(Projection::Perspective(perspective)) => {
+                    perspective.fov = clamp_between(
+                        perspective.fov * zoom,
+                        scroll_settings.min_fov,
+                        scroll_settings.max_fov,
+                    );
+                }
+                Some(Projection::Orthographic(orthographic)) => {
+                    orthographic.scale = clamp_between(
+                        orthographic.scale * zoom,
+                        scroll_settings.min_scale,
+                        scroll_settings.max_scale,
+                    );
+                }
+                None => (),
+            }
+        } else {
+            let settings = match cam_settings {
+                Some(cam_settings) => cam_settings.into_inner(),
+                None => settings.as_mut(),
+            };
+            settings.speed = clamp_between(
+                settings.speed * scroll_settings.speed_factor.powf(lines),
+                scroll_settings.min_speed,
+                scroll_settings.max_speed,
+            );
+        }
+    }
+}
+
+/// Grabs and releases the cursor according to [`CursorGrabSettings`]
+///
+/// Only the focused window can be grabbed, and only if a flycam renders to it.
 fn cursor_grab(
-    keys: Res<Input<KeyCode>>,
+    input: FlyCamInput,
     key_bindings: Res<KeysBindings>,
+    grab_settings: Res<CursorGrabSettings>,
+    mut focus_events: EventReader<WindowFocused>,
     mut windows: ResMut<Windows>,
+    query: Query<Option<&Camera>, With<FlyCam>>,
 ) {
-    if let Some(window) = windows.get_primary_mut() {
-        if keys.just_pressed(key_bindings.toggle_grab_cursor) {
-            toggle_grab_cursor(window);
+    let focus_events: Vec<WindowFocused> = focus_events.iter().cloned().collect();
+    let flycam_windows: Vec<WindowId> = query.iter().filter_map(flycam_window).collect();
+
+    for window in windows.iter_mut() {
+        // Leave the cursor of windows without a flycam to the app
+        if !flycam_windows.contains(&window.id()) {
+            continue;
+        }
+        let lost_focus = focus_events
+            .iter()
+            .rfind(|ev| ev.id == window.id())
+            .is_some_and(|ev| !ev.focused);
+        let grabbed = window.cursor_grab_mode() != CursorGrabMode::None;
+
+        let released_hold = grabbed
+            && grab_settings
+                .hold_to_grab
+                .is_some_and(|button| input.mouse_buttons.just_released(button));
+
+        // Releasing is always allowed, grabbing only while input is not blocked
+        if (grab_settings.release_on_focus_loss && lost_focus) || released_hold {
+            release_cursor(window);
+            continue;
+        }
+        if input.is_blocked() || !window.is_focused() {
+            continue;
         }
-    } else {
-        warn!("Primary window not found for `cursor_grab`!");
+
+        if input.just_pressed(&key_bindings.toggle_grab_cursor, input.default_gamepad()) {
+            toggle_grab_cursor(window, grab_settings.mode);
+        } else if grab_settings.grab_on_click
+            && !grabbed
+            && input.mouse_buttons.just_pressed(MouseButton::Left)
+        {
+            grab_cursor(window, grab_settings.mode);
+        } else if let Some(button) = grab_settings.hold_to_grab {
+            if !grabbed && input.mouse_buttons.just_pressed(button) {
+                grab_cursor(window, grab_settings.mode);
+            }
+        }
+    }
+}
+
+/// Sets [`FlyCamInputBlocked::ui`] while the free cursor interacts with a UI node
+#[cfg(feature = "bevy_ui")]
+fn block_input_over_ui(
+    windows: Res<Windows>,
+    interactions: Query<&Interaction>,
+    mut blocked: ResMut<FlyCamInputBlocked>,
+) {
+    let grabbed = windows
+        .iter()
+        .any(|window| window.is_focused() && window.cursor_grab_mode() != CursorGrabMode::None);
+    let over_ui = !grabbed
+        && interactions
+            .iter()
+            .any(|interaction| *interaction != Interaction::None);
+
+    if blocked.ui != over_ui {
+        blocked.ui = over_ui;
     }
 }
 
@@ -185,14 +1475,43 @@ fn cursor_grab(
 pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<InputState>()
-            .init_resource::<MovementSettings>()
-            .init_resource::<KeysBindings>()
-            .add_startup_system(setup_player)
-            .add_startup_system(initial_grab_cursor)
-            .add_system(player_move)
-            .add_system(player_look)
-            .add_system(cursor_grab);
+        app.add_plugin(NoCameraPlayerPlugin)
+            .add_startup_system(setup_player);
+    }
+}
+
+/// [`NoCameraPlayerPlugin`] with options chosen when the plugin is built
+///
+/// The options are inserted as resources, replacing any added before, and can still
+/// be changed while the app runs.
+#[derive(Clone)]
+pub struct FlyCamPlugin {
+    /// Preset the [`KeysBindings`] resource is created from
+    pub layout: KeyboardLayout,
+    /// When the cursor is grabbed and released
+    pub grab: CursorGrabSettings,
+    /// Spawn a camera, like [`PlayerPlugin`]
+    pub spawn_camera: bool,
+}
+
+impl Default for FlyCamPlugin {
+    fn default() -> Self {
+        Self {
+            layout: KeyboardLayout::Qwerty,
+            grab: CursorGrabSettings::default(),
+            spawn_camera: true,
+        }
+    }
+}
+
+impl Plugin for FlyCamPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(NoCameraPlayerPlugin)
+            .insert_resource(KeysBindings::from_layout(self.layout))
+            .insert_resource(self.grab.clone());
+        if self.spawn_camera {
+            app.add_startup_system(setup_player);
+        }
     }
 }
 
@@ -200,12 +1519,136 @@ impl Plugin for PlayerPlugin {
 pub struct NoCameraPlayerPlugin;
 impl Plugin for NoCameraPlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<InputState>()
+        app.register_type::<InputState>()
+            .register_type::<FlyCamVelocity>()
+            .register_type::<MovementMode>()
+            .register_type::<MovementSettings>()
+            .register_type::<InputBinding>()
+            .register_type::<Vec<InputBinding>>()
+            .register_type::<KeysBindings>()
+            .register_type::<KeyboardLayout>()
+            .register_type::<Option<Gamepad>>()
+            .register_type::<GamepadControls>()
+            .register_type::<ScrollSettings>()
+            .register_type::<GrabMode>()
+            .register_type::<Option<MouseButton>>()
+            .register_type::<CursorGrabSettings>()
+            .register_type::<FlyCam>()
+            .register_type::<FlyCamMode>()
+            .register_type::<ActiveFlyCam>()
+            .register_type::<SwitchFlyCam>()
+            .register_type::<FlyCamInputBlocked>()
+            .register_type::<OrbitCam>()
+            .register_type::<OrbitSettings>()
+            .register_type::<WalkSettings>()
+            .register_type::<Heightfield>()
+            .register_type::<Option<Heightfield>>()
+            .register_type::<FlyCamGround>()
+            .register_type::<FlyCamCollider>()
+            .register_type::<CollisionSettings>()
+            .register_type::<BoundsBox>()
+            .register_type::<Vec<BoundsBox>>()
+            .register_type::<FlyCamBoundsHit>()
             .init_resource::<MovementSettings>()
             .init_resource::<KeysBindings>()
-            .add_startup_system(initial_grab_cursor)
+            .init_resource::<ScrollSettings>()
+            .init_resource::<CursorGrabSettings>()
+            .init_resource::<GamepadControls>()
+            .init_resource::<FlyCamInputBlocked>()
+            .init_resource::<OrbitSettings>()
+            .init_resource::<WalkSettings>()
+            .init_resource::<FlyCamGround>()
+            .init_resource::<CollisionSettings>()
+            .add_event::<SwitchFlyCam>()
+            .add_event::<FlyCamBoundsHit>()
+            .add_system(setup_input_state)
+            .add_system(initial_grab_cursor)
+            .add_system(switch_flycam)
+            .add_system(cycle_flycam_mode)
+            .add_system(apply_flycam_mode.after(cycle_flycam_mode))
             .add_system(player_move)
             .add_system(player_look)
+            .add_system(scroll)
+            .add_system(orbit::orbit)
+            .add_system(
+                bounds::clamp_to_bounds
+                    .after(player_move)
+                    .after(orbit::orbit),
+            )
             .add_system(cursor_grab);
+
+        #[cfg(feature = "bevy_ui")]
+        app.add_system(block_input_over_ui.before(cursor_grab));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Distance covered in `seconds` at `fps` with `target` held from `velocity`
+    fn travel(settings: &MovementSettings, velocity: Vec3, target: Vec3, fps: f32) -> f32 {
+        let (mut velocity, mut distance) = (velocity, Vec3::ZERO);
+        for _ in 0..(fps as usize) {
+            let moved;
+            (velocity, moved) = settings.accelerate(velocity, target, 1. / fps);
+            distance += moved;
+        }
+        distance.length()
+    }
+
+    #[test]
+    fn input_state_reproduces_looking_at_pose() {
+        for up in [Vec3::Y, Vec3::Z, Vec3::NEG_Y, Vec3::X] {
+            let rotation = Transform::from_xyz(1., 2., 3.)
+                .looking_at(Vec3::new(-2., 1.5, 4.5), up)
+                .rotation;
+            let mut state = InputState::from_rotation(rotation, up);
+            let settings = MovementSettings {
+                up,
+                ..Default::default()
+            };
+            let rebuilt = state.apply_look(0., 0., &settings);
+            for axis in [Vec3::NEG_Z, Vec3::Y] {
+                let error = (rebuilt * axis - rotation * axis).length();
+                assert!(error < 1e-5, "{up}: {axis} is off by {error}");
+            }
+        }
+    }
+
+    #[test]
+    fn clamp_between_accepts_reversed_and_nan_limits() {
+        assert_eq!(clamp_between(2., -1., 1.), 1.);
+        assert_eq!(clamp_between(-2., -1., 1.), -1.);
+        assert_eq!(clamp_between(0.5, -1., 1.), 0.5);
+        // Reversed
+        assert_eq!(clamp_between(2., 1., -1.), 1.);
+        assert_eq!(clamp_between(-2., 1., -1.), -1.);
+        // A NaN limit leaves that side unbounded
+        assert_eq!(clamp_between(5., f32::NAN, 1.), 1.);
+        assert_eq!(clamp_between(-5., f32::NAN, 1.), -5.);
+        assert_eq!(clamp_between(5., -1., f32::NAN), 5.);
+        assert_eq!(clamp_between(-5., -1., f32::NAN), -1.);
+        assert_eq!(clamp_between(5., f32::NAN, f32::NAN), 5.);
+    }
+
+    #[test]
+    fn coasting_distance_is_frame_rate_independent() {
+        let settings = MovementSettings::default();
+        // Exactly speed / friction once the camera has come to rest
+        for fps in [30., 60., 144., 600.] {
+            let distance = travel(&settings, Vec3::X * 12., Vec3::ZERO, fps);
+            assert!((distance - 1.2).abs() < 1e-3, "{fps} fps: {distance}");
+        }
+    }
+
+    #[test]
+    fn accelerating_distance_is_frame_rate_independent() {
+        let settings = MovementSettings::default();
+        // 0.2 s ramping up to 12 units per second, then 0.8 s at full speed
+        for fps in [30., 60., 144., 600.] {
+            let distance = travel(&settings, Vec3::ZERO, Vec3::X * 12., fps);
+            assert!((distance - 10.8).abs() < 1e-3, "{fps} fps: {distance}");
+        }
     }
 }
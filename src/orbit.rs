@@ -0,0 +1,151 @@
+use bevy::ecs::event::Events;
+use bevy::input::mouse::{MouseMotion, MouseWheel};
+use bevy::prelude::*;
+
+use crate::{
+    clamp_between, focused_window, wheel_lines, ActiveFlyCam, FlyCam, FlyCamInput, GamepadControls,
+    InputState, KeysBindings, MovementSettings,
+};
+
+/// Turns a [`FlyCam`] into an orbit camera rotating around `pivot`
+///
+/// While this component is present, [`KeysBindings::orbit_rotate`] drags rotate the
+/// camera around the pivot, the mouse wheel moves it along the view ray and
+/// [`KeysBindings::orbit_pan`] drags move the pivot. Removing it goes back to flying
+/// from the current pose.
+///
+/// Prefer switching with [`FlyCamMode`](crate::FlyCamMode). When inserting it directly, use
+/// [`OrbitCam::in_front_of`] to switch without moving the camera. Any other pivot
+/// makes the camera turn towards it on the next input.
+#[derive(Component, Clone, Copy, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct OrbitCam {
+    pub pivot: Vec3,
+}
+
+impl OrbitCam {
+    /// Orbits around the point `distance` in front of the camera, so it stays where it is
+    pub fn in_front_of(transform: &Transform, distance: f32) -> Self {
+        Self {
+            pivot: transform.translation + transform.forward() * distance,
+        }
+    }
+}
+
+/// Mouse sensitivity and zoom limits of [`OrbitCam`]s
+///
+/// Up axis and pitch limits come from [`MovementSettings`]. Can be
+/// [overridden per camera](crate#per-camera-settings).
+#[derive(Resource, Component, Clone, Reflect)]
+#[reflect(Resource, Component)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct OrbitSettings {
+    pub sensitivity: f32,
+    /// Pivot movement per pixel dragged, relative to the distance to the pivot
+    pub pan_sensitivity: f32,
+    /// Distance is divided by this per line scrolled up
+    pub zoom_factor: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    /// Distance of the pivot ahead of the camera when switching to [`FlyCamMode::Orbit`](crate::FlyCamMode::Orbit)
+    pub pivot_distance: f32,
+}
+
+impl Default for OrbitSettings {
+    fn default() -> Self {
+        Self {
+            sensitivity: 0.00012,
+            pan_sensitivity: 0.002,
+            zoom_factor: 1.1,
+            min_distance: 0.1,
+            max_distance: 1000.,
+            pivot_distance: 10.,
+        }
+    }
+}
+
+/// Rotates, zooms and pans the active [`OrbitCam`]
+///
+/// Dragging works with a free cursor, only the window has to be focused.
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+pub(crate) fn orbit(
+    time: Res<Time>,
+    windows: Res<Windows>,
+    settings: Res<MovementSettings>,
+    orbit_settings: Res<OrbitSettings>,
+    key_bindings: Res<KeysBindings>,
+    motion: Res<Events<MouseMotion>>,
+    mut wheel: EventReader<MouseWheel>,
+    input: FlyCamInput,
+    mut query: Query<
+        (
+            &mut OrbitCam,
+            &mut InputState,
+            &mut Transform,
+            Option<&MovementSettings>,
+            Option<&OrbitSettings>,
+            Option<&KeysBindings>,
+            Option<&GamepadControls>,
+            Option<&Camera>,
+        ),
+        (With<FlyCam>, With<ActiveFlyCam>),
+    >,
+) {
+    let lines = wheel_lines(&mut wheel);
+    let lines = if input.is_blocked() { 0. } else { lines };
+    for (
+        mut orbit,
+        mut state,
+        mut transform,
+        cam_settings,
+        cam_orbit_settings,
+        cam_bindings,
+        cam_controls,
+        camera,
+    ) in query.iter_mut()
+    {
+        let window = match focused_window(&windows, camera) {
+            Some(window) => window,
+            None => continue,
+        };
+        let settings = cam_settings.unwrap_or(&settings);
+        let orbit_settings = cam_orbit_settings.unwrap_or(&orbit_settings);
+        let key_bindings = cam_bindings.unwrap_or(&key_bindings);
+        let state = state.as_mut();
+        state.sync_if_changed(transform.rotation, settings.up_axis());
+
+        let controls = input.controls(cam_controls);
+        let gamepad = input.gamepad(controls);
+        let rotating = input.pressed(&key_bindings.orbit_rotate, gamepad, true);
+        let panning = input.pressed(&key_bindings.orbit_pan, gamepad, true);
+        let delta: Vec2 = state.reader_motion.iter(&motion).map(|ev| ev.delta).sum();
+
+        let (mut pitch, mut yaw) = input.look_stick(gamepad, controls, time.delta_seconds());
+        if rotating {
+            // Same scaling as `player_look`, so both modes turn at the same rate
+            let window_scale = window.height().min(window.width());
+            pitch -= (orbit_settings.sensitivity * delta.y * window_scale).to_radians();
+            yaw -= (orbit_settings.sensitivity * delta.x * window_scale).to_radians();
+        }
+
+        let panned = panning && delta != Vec2::ZERO;
+        if pitch == 0. && yaw == 0. && lines == 0. && !panned {
+            continue;
+        }
+
+        let mut distance = transform.translation.distance(orbit.pivot);
+        if panned {
+            let scale = distance * orbit_settings.pan_sensitivity;
+            orbit.pivot += (transform.up() * delta.y - transform.right() * delta.x) * scale;
+        }
+        // Scrolling up moves towards the pivot
+        distance = clamp_between(
+            distance * orbit_settings.zoom_factor.powf(-lines),
+            orbit_settings.min_distance,
+            orbit_settings.max_distance,
+        );
+
+        transform.rotation = state.apply_look(pitch, yaw, settings);
+        transform.translation = orbit.pivot + transform.back() * distance;
+    }
+}
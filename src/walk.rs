@@ -0,0 +1,305 @@
+use std::fmt;
+use std::sync::Arc;
+
+use bevy::prelude::*;
+
+use crate::MovementSettings;
+
+/// Gravity, jump and eye height used in [`FlyCamMode::Walk`](crate::FlyCamMode::Walk)
+///
+/// Walking moves on the horizontal plane with the acceleration, friction and speed
+/// multipliers of [`MovementSettings`], but at `speed` instead of its flying speed.
+/// Can be [overridden per camera](crate#per-camera-settings).
+#[derive(Resource, Component, Clone, Reflect)]
+#[reflect(Resource, Component)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct WalkSettings {
+    /// Walking speed, in units per second
+    pub speed: f32,
+    /// Downward acceleration, in units per second squared
+    pub gravity: f32,
+    /// Upward speed when jumping, in units per second
+    pub jump_speed: f32,
+    /// Height of the camera above the ground
+    pub eye_height: f32,
+    /// Ground lower than this below the feet is stepped down onto instead of falling
+    pub snap_distance: f32,
+}
+
+impl Default for WalkSettings {
+    fn default() -> Self {
+        Self {
+            speed: 4.,
+            gravity: 9.81,
+            jump_speed: 5.,
+            eye_height: 1.7,
+            snap_distance: 0.25,
+        }
+    }
+}
+
+type HeightFn = Arc<dyn Fn(Vec3) -> f32 + Send + Sync>;
+
+/// Ground height below a walking [`FlyCam`](crate::FlyCam)
+///
+/// Heights are measured along [`MovementSettings::up`]. A height function takes
+/// precedence over the heightfield, and `base_height` is used where neither gives
+/// a height.
+#[derive(Resource, Clone, Default, Reflect)]
+#[reflect(Resource)]
+pub struct FlyCamGround {
+    pub base_height: f32,
+    pub heightfield: Option<Heightfield>,
+    #[reflect(ignore)]
+    height_fn: Option<HeightFn>,
+}
+
+impl FlyCamGround {
+    /// Flat ground at `height`
+    pub fn flat(height: f32) -> Self {
+        Self {
+            base_height: height,
+            ..Default::default()
+        }
+    }
+
+    /// Ground whose height at a world position is given by `height_fn`
+    pub fn from_fn(height_fn: impl Fn(Vec3) -> f32 + Send + Sync + 'static) -> Self {
+        Self {
+            height_fn: Some(Arc::new(height_fn)),
+            ..Default::default()
+        }
+    }
+
+    /// Ground sampled from `heightfield`, with `base_height` outside of it
+    pub fn from_heightfield(heightfield: Heightfield) -> Self {
+        Self {
+            heightfield: Some(heightfield),
+            ..Default::default()
+        }
+    }
+
+    /// Ground height below `position`, for ground perpendicular to `up`
+    pub fn height(&self, position: Vec3, up: Vec3) -> f32 {
+        if let Some(height_fn) = &self.height_fn {
+            height_fn(position)
+        } else {
+            self.heightfield
+                .as_ref()
+                .and_then(|heightfield| heightfield.height(Heightfield::coordinates(position, up)))
+                .unwrap_or(self.base_height)
+        }
+    }
+}
+
+impl fmt::Debug for FlyCamGround {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FlyCamGround")
+            .field("base_height", &self.base_height)
+            .field("heightfield", &self.heightfield)
+            .field("height_fn", &self.height_fn.is_some())
+            .finish()
+    }
+}
+
+/// Grid of ground heights on the plane perpendicular to [`MovementSettings::up`]
+///
+/// The grid uses the two world axes other than the one closest to `up`: X and Z for
+/// Y-up scenes, X and Y for Z-up scenes. `heights` holds `columns` samples along the
+/// first axis per row, with rows along the second, spaced `cell_size` apart starting
+/// at `origin`. Heights between samples are interpolated.
+#[derive(Clone, Debug, Default, Reflect, FromReflect)]
+pub struct Heightfield {
+    /// Grid coordinates of the first sample, e.g. X and Z for Y-up scenes
+    pub origin: Vec2,
+    pub cell_size: f32,
+    pub columns: usize,
+    pub heights: Vec<f32>,
+}
+
+impl Heightfield {
+    /// Grid coordinates of `position` projected onto the plane perpendicular to `up`
+    pub fn coordinates(position: Vec3, up: Vec3) -> Vec2 {
+        let up = up.normalize_or_zero();
+        let flat = position - up * position.dot(up);
+        let up = up.abs();
+        if up.y >= up.x && up.y >= up.z {
+            Vec2::new(flat.x, flat.z)
+        } else if up.z >= up.x {
+            Vec2::new(flat.x, flat.y)
+        } else {
+            Vec2::new(flat.y, flat.z)
+        }
+    }
+
+    /// Interpolated height at grid `coordinates`, or `None` outside of the grid
+    pub fn height(&self, coordinates: Vec2) -> Option<f32> {
+        if self.columns == 0 || self.cell_size <= 0. {
+            return None;
+        }
+        let rows = self.heights.len() / self.columns;
+        let cell = (coordinates - self.origin) / self.cell_size;
+        let max = Vec2::new(self.columns as f32 - 1., rows as f32 - 1.);
+        if rows == 0 || cell.cmplt(Vec2::ZERO).any() || cell.cmpgt(max).any() {
+            return None;
+        }
+
+        // Clamp so the far edges interpolate within the last cell
+        let corner = cell.floor().min(max - 1.).max(Vec2::ZERO);
+        let t = cell - corner;
+        let (column, row) = (corner.x as usize, corner.y as usize);
+        let sample = |c: usize, r: usize| {
+            self.heights[r.min(rows - 1) * self.columns + c.min(self.columns - 1)]
+        };
+        let near = sample(column, row) * (1. - t.x) + sample(column + 1, row) * t.x;
+        let far = sample(column, row + 1) * (1. - t.x) + sample(column + 1, row + 1) * t.x;
+        Some(near * (1. - t.y) + far * t.y)
+    }
+}
+
+/// Moves a walking camera towards `target` velocity, applying gravity and jumps and
+/// keeping it `eye_height` above the ground
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn walk(
+    velocity: &mut Vec3,
+    translation: &mut Vec3,
+    target: Vec3,
+    jump: bool,
+    settings: &MovementSettings,
+    walk_settings: &WalkSettings,
+    ground: &FlyCamGround,
+    dt: f32,
+) {
+    let up = settings.up_axis();
+    let floor = |translation: Vec3| ground.height(translation, up) + walk_settings.eye_height;
+    let height = translation.dot(up);
+    let vertical = velocity.dot(up);
+    let grounded = vertical <= 0. && height <= floor(*translation) + walk_settings.snap_distance;
+
+    let (horizontal, moved) = settings.accelerate(*velocity - up * vertical, target, dt);
+    let (vertical, rise) = match (grounded, jump) {
+        (true, true) => (walk_settings.jump_speed, walk_settings.jump_speed * dt),
+        (true, false) => (0., 0.),
+        (false, _) => {
+            let next = vertical - walk_settings.gravity * dt;
+            (next, (vertical + next) * 0.5 * dt)
+        }
+    };
+    *velocity = horizontal + up * vertical;
+    *translation += moved + up * rise;
+
+    // Stand on the ground, and follow it down slopes instead of falling off them
+    let offset = floor(*translation) - translation.dot(up);
+    if offset > 0. || (grounded && !jump && offset > -walk_settings.snap_distance) {
+        *translation += up * offset;
+        *velocity -= up * velocity.dot(up).min(0.);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2x2 samples one unit apart, rising by 1 along the first axis and 2 along the second
+    fn grid() -> Heightfield {
+        Heightfield {
+            origin: Vec2::ZERO,
+            cell_size: 1.,
+            columns: 2,
+            heights: vec![0., 1., 2., 3.],
+        }
+    }
+
+    #[test]
+    fn heightfield_interpolates_cell() {
+        let grid = grid();
+        assert_eq!(grid.height(Vec2::new(0., 0.)), Some(0.));
+        assert_eq!(grid.height(Vec2::new(1., 0.)), Some(1.));
+        assert_eq!(grid.height(Vec2::new(0., 1.)), Some(2.));
+        assert_eq!(grid.height(Vec2::new(1., 1.)), Some(3.));
+        assert_eq!(grid.height(Vec2::new(0.5, 0.5)), Some(1.5));
+    }
+
+    #[test]
+    fn heightfield_outside_grid_is_none() {
+        let grid = grid();
+        for coordinates in [
+            Vec2::new(-0.1, 0.5),
+            Vec2::new(1.1, 0.5),
+            Vec2::new(0.5, -0.1),
+            Vec2::new(0.5, 1.1),
+        ] {
+            assert_eq!(grid.height(coordinates), None, "{coordinates}");
+        }
+        assert_eq!(Heightfield::default().height(Vec2::ZERO), None);
+    }
+
+    #[test]
+    fn heightfield_single_column() {
+        let grid = Heightfield {
+            origin: Vec2::new(2., 0.),
+            cell_size: 1.,
+            columns: 1,
+            heights: vec![1., 3.],
+        };
+        assert_eq!(grid.height(Vec2::new(2., 0.5)), Some(2.));
+        assert_eq!(grid.height(Vec2::new(2., 1.)), Some(3.));
+        assert_eq!(grid.height(Vec2::new(2.5, 0.5)), None);
+    }
+
+    #[test]
+    fn coordinates_follow_up_axis() {
+        let position = Vec3::new(1., 2., 3.);
+        assert_eq!(
+            Heightfield::coordinates(position, Vec3::Y),
+            Vec2::new(1., 3.)
+        );
+        assert_eq!(
+            Heightfield::coordinates(position, Vec3::Z),
+            Vec2::new(1., 2.)
+        );
+        assert_eq!(
+            Heightfield::coordinates(position, Vec3::X),
+            Vec2::new(2., 3.)
+        );
+        assert_eq!(
+            Heightfield::coordinates(position, Vec3::NEG_Z),
+            Vec2::new(1., 2.)
+        );
+    }
+
+    #[test]
+    fn falls_onto_ground_at_eye_height() {
+        let walk_settings = WalkSettings::default();
+        for up in [Vec3::Y, Vec3::Z] {
+            let settings = MovementSettings {
+                up,
+                ..Default::default()
+            };
+            // Small enough that sampling the wrong plane misses it for Z-up
+            let ground = FlyCamGround::from_heightfield(Heightfield {
+                origin: Vec2::splat(-1.),
+                cell_size: 2.,
+                columns: 2,
+                heights: vec![1.; 4],
+            });
+            let mut translation = up * 10.;
+            let mut velocity = Vec3::ZERO;
+            for _ in 0..180 {
+                walk(
+                    &mut velocity,
+                    &mut translation,
+                    Vec3::ZERO,
+                    false,
+                    &settings,
+                    &walk_settings,
+                    &ground,
+                    1. / 60.,
+                );
+            }
+            let height = 1. + walk_settings.eye_height;
+            assert!((translation - up * height).length() < 1e-5, "{translation}");
+            assert_eq!(velocity, Vec3::ZERO);
+        }
+    }
+}